@@ -0,0 +1,168 @@
+//! Atomic file replacement shared by the file-backed adapters.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Distinguishes temp files created by concurrent writes within this process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// How hard a file adapter works to make a write survive a crash or power loss.
+///
+/// Every level writes to a temporary file and renames it over the target, so a
+/// reader never observes a half-written document. The levels differ in whether
+/// the data and the rename are forced to stable storage before `write` returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Durability {
+    /// Leave flushing to the operating system.
+    ///
+    /// Fastest option, suitable for tests and throwaway data. After a power
+    /// loss the file may be empty or hold an older version.
+    None,
+    /// `fsync` the temporary file before renaming it over the target.
+    ///
+    /// The new contents are on disk, but the rename itself may be lost.
+    Data,
+    /// `fsync` the temporary file, rename it, then `fsync` the parent directory.
+    ///
+    /// After `write` returns the new document survives a crash.
+    #[default]
+    Full,
+}
+
+/// Replace `path` with `bytes` via a temporary file and a rename.
+///
+/// The temporary file lives in the same directory as `path` and has a name
+/// unique to this process and call, so concurrent writers never share one.
+/// It is removed again if any step before the rename fails.
+///
+/// `before_rename` runs once the new contents are safely in the temp file,
+/// while `path` still holds the previous version.
+pub(crate) fn write(
+    path: &Path,
+    bytes: &[u8],
+    durability: Durability,
+    before_rename: impl FnOnce() -> io::Result<()>,
+) -> io::Result<()> {
+    let temp_path = temp_path(path);
+
+    let result = write_temp(&temp_path, bytes, durability)
+        .and_then(|()| before_rename())
+        .and_then(|()| fs::rename(&temp_path, path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if durability == Durability::Full {
+        sync_parent(path)?;
+    }
+
+    Ok(())
+}
+
+fn write_temp(temp_path: &Path, bytes: &[u8], durability: Durability) -> io::Result<()> {
+    let mut file = File::options().write(true).create_new(true).open(temp_path)?;
+    file.write_all(bytes)?;
+    if durability != Durability::None {
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Build a fresh temp file name for `path`: `.<name>.<pid>.<counter>.tmp`.
+fn temp_path(path: &Path) -> PathBuf {
+    let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let name = format!(
+        "{}{}.{}.tmp",
+        temp_prefix(path),
+        std::process::id(),
+        counter
+    );
+    path.with_file_name(name)
+}
+
+fn temp_prefix(path: &Path) -> String {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    format!(".{}.", file_name)
+}
+
+/// Remove temp files for `path` left behind by processes that crashed mid-write.
+///
+/// Temp files owned by this process, by a process that is still running, or
+/// written within the last hour are left alone. Failures are ignored: a leftover temp file is harmless.
+pub(crate) fn remove_stale_temps(path: &Path) {
+    let prefix = temp_prefix(path);
+    let dir = parent_dir(path);
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(pid) = name
+            .to_str()
+            .and_then(|n| n.strip_prefix(&prefix))
+            .and_then(|n| n.strip_suffix(".tmp"))
+            .and_then(|n| n.split_once('.'))
+            .and_then(|(pid, counter)| {
+                counter.parse::<u64>().ok()?;
+                pid.parse::<u32>().ok()
+            })
+        else {
+            continue;
+        };
+
+        if pid != std::process::id() && !owner_alive(pid, &entry) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// A process that `/proc` does not list may still be running in another PID
+/// namespace, or be hidden by `hidepid`, so its temp file also has to be old.
+#[cfg(target_os = "linux")]
+fn owner_alive(pid: u32, entry: &fs::DirEntry) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists() || recently_modified(entry)
+}
+
+/// Without `/proc` there is no portable liveness check, so a temp file counts
+/// as abandoned once it is old.
+#[cfg(not(target_os = "linux"))]
+fn owner_alive(_pid: u32, entry: &fs::DirEntry) -> bool {
+    recently_modified(entry)
+}
+
+/// Whether the temp file was written within the last hour; no write takes that long.
+fn recently_modified(entry: &fs::DirEntry) -> bool {
+    use std::time::{Duration, SystemTime};
+
+    const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+    entry
+        .metadata()
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < STALE_TEMP_AGE)
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    }
+}
+
+/// Flush the directory entry for `path` so a completed rename is durable.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    File::open(parent_dir(path))?.sync_all()
+}
+
+/// Directories cannot be opened for syncing on this platform, so this is a no-op.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! Numbered backups of previous file versions (`db.json.1`, `db.json.2`, ...).
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Path of backup number `k` for `path`; `1` is the most recent.
+pub(crate) fn path(path: &Path, k: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", k));
+    path.with_file_name(name)
+}
+
+/// Shift existing backups up by one and preserve the current file as backup 1,
+/// keeping at most `count` backups.
+///
+/// The current file is hard-linked rather than moved, so `path` never
+/// disappears; filesystems without hard links fall back to a copy.
+pub(crate) fn rotate(file: &Path, count: usize) -> io::Result<()> {
+    if count == 0 || !file.exists() {
+        return Ok(());
+    }
+
+    remove_if_exists(&path(file, count))?;
+    for k in (1..count).rev() {
+        let from = path(file, k);
+        if from.exists() {
+            fs::rename(from, path(file, k + 1))?;
+        }
+    }
+
+    let newest = path(file, 1);
+    if fs::hard_link(file, &newest).is_err() {
+        fs::copy(file, &newest)?;
+    }
+
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
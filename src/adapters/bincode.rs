@@ -0,0 +1,40 @@
+//! bincode, the most compact and fastest of the binary formats.
+
+use crate::adapters::file::{File, FileSync};
+use crate::adapters::format::Format;
+use crate::core::{Result, SaberError};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// bincode documents, using bincode's standard configuration.
+///
+/// The encoding carries no field names or types, so it only suits data whose
+/// shape is fixed: changing the type invalidates existing files, and serde
+/// features that need self-describing input, such as `#[serde(flatten)]`,
+/// untagged enums or `serde_json::Value`, cannot be decoded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+impl Format for Bincode {
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>> {
+        ::bincode::serde::encode_to_vec(data, ::bincode::config::standard())
+            .map_err(|e| SaberError::Serialization(e.into()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        let (data, read) =
+            ::bincode::serde::decode_from_slice(bytes, ::bincode::config::standard())
+                .map_err(|e| SaberError::Serialization(e.into()))?;
+        if read != bytes.len() {
+            return Err(SaberError::Serialization(
+                format!("{} trailing bytes after the document", bytes.len() - read).into(),
+            ));
+        }
+        Ok(data)
+    }
+}
+
+/// bincode file adapter for synchronous operations
+pub type BincodeFileSync = FileSync<Bincode>;
+
+/// bincode file adapter for asynchronous operations
+pub type BincodeFile = File<Bincode>;
@@ -0,0 +1,28 @@
+//! CBOR (RFC 8949), a compact binary format.
+
+use crate::adapters::file::{File, FileSync};
+use crate::adapters::format::Format;
+use crate::core::{Result, SaberError};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// CBOR documents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+impl Format for Cbor {
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(data, &mut bytes).map_err(|e| SaberError::Serialization(e.into()))?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        ciborium::from_reader(bytes).map_err(|e| SaberError::Serialization(e.into()))
+    }
+}
+
+/// CBOR file adapter for synchronous operations
+pub type CborFileSync = FileSync<Cbor>;
+
+/// CBOR file adapter for asynchronous operations
+pub type CborFile = File<Cbor>;
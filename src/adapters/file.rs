@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use crate::adapters::atomic::Durability;
+use crate::adapters::lock::LockMode;
+use crate::adapters::recovery::{self, Recovery, RecoveryReport};
+use crate::adapters::format::Format;
+use crate::adapters::json::Json;
+use crate::adapters::store::FileStore;
+use crate::adapters::{Adapter, AdapterSync};
+use crate::core::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// File adapter for synchronous operations, storing data in format `F`
+///
+/// Use an alias such as [`JsonFileSync`](crate::JsonFileSync) for a built-in
+/// format, or implement [`Format`] for your own.
+pub struct FileSync<F: Format = Json> {
+    store: FileStore,
+    format: F,
+    recovery: Recovery,
+    last_recovery: Mutex<Option<RecoveryReport>>,
+}
+
+impl<F: Format + Default> FileSync<F> {
+    /// Create a new file adapter
+    ///
+    /// Writes use [`Durability::Full`] unless configured otherwise. Temp files
+    /// left next to `path` by a crashed writer are removed.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self::with_format(path, F::default())
+    }
+}
+
+impl<F: Format> FileSync<F> {
+    /// Create a new file adapter using a configured `format`
+    pub fn with_format(path: impl AsRef<Path>, format: F) -> Self {
+        Self {
+            store: FileStore::new(path.as_ref()),
+            format,
+            recovery: Recovery::default(),
+            last_recovery: Mutex::new(None),
+        }
+    }
+
+    /// Set how much effort writes spend making data crash-safe
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.store.set_durability(durability);
+        self
+    }
+
+    /// Keep the previous `count` versions of the file as `<file>.1` (newest)
+    /// through `<file>.<count>` (oldest), rotated on every write
+    pub fn with_backups(mut self, count: usize) -> Self {
+        self.store.set_backups(count);
+        self
+    }
+
+    /// Set how this adapter locks the file against other processes
+    pub fn with_lock(mut self, mode: LockMode) -> Self {
+        self.store.set_lock_mode(mode);
+        self
+    }
+
+    /// Set what happens when the file exists but cannot be decoded
+    pub fn with_recovery(mut self, policy: Recovery) -> Self {
+        self.recovery = policy;
+        self
+    }
+
+    /// Fail writes with [`SaberError::Conflict`](crate::SaberError::Conflict)
+    /// if the file changed since this adapter last read or wrote it
+    ///
+    /// Lets several processes share a file without locks: a writer that lost
+    /// the race gets an error instead of silently overwriting the other's
+    /// change, and can reload and retry. The check happens just before the
+    /// file is replaced, so only a writer finishing in that instant can slip
+    /// through; use [`with_lock`](Self::with_lock) if that matters.
+    pub fn with_version_check(mut self) -> Self {
+        self.store.set_check_version(true);
+        self
+    }
+
+    /// Version of the file as this adapter last read or wrote it
+    ///
+    /// A checksum of the contents, usable as an etag; `None` if the file did
+    /// not exist or has not been read yet.
+    pub fn version(&self) -> Option<String> {
+        self.store.version()
+    }
+
+    /// Report of the most recent recovery from a corrupt file, if any
+    pub fn last_recovery(&self) -> Option<RecoveryReport> {
+        self.last_recovery.lock().unwrap().clone()
+    }
+}
+
+impl<T, F> AdapterSync<T> for FileSync<F>
+where
+    T: Serialize + DeserializeOwned,
+    F: Format,
+{
+    fn read(&self) -> Result<Option<T>> {
+        let Some(bytes) = self.store.read()? else {
+            return Ok(None);
+        };
+
+        match self.format.decode(&bytes) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) => {
+                let decode = |bytes: &[u8]| self.format.decode(bytes);
+                let (data, report) = recovery::recover(&self.store, self.recovery, e, decode)?;
+                *self.last_recovery.lock().unwrap() = Some(report);
+                Ok(data)
+            }
+        }
+    }
+
+    fn write(&self, data: &T) -> Result<()> {
+        let bytes = self.format.encode(data)?;
+
+        // Atomic write: write to temp file, then rename
+        self.store.write(&bytes)
+    }
+
+    fn read_backup(&self, k: usize) -> Result<Option<T>> {
+        match self.store.read_backup(k)? {
+            Some(bytes) => Ok(Some(self.format.decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn changed(&self) -> Result<bool> {
+        self.store.changed()
+    }
+
+    fn write_snapshot(&self, name: &str, data: &T) -> Result<()> {
+        self.store.write_snapshot(name, &self.format.encode(data)?)
+    }
+
+    fn read_snapshot(&self, name: &str) -> Result<Option<T>> {
+        match self.store.read_snapshot(name)? {
+            Some(bytes) => Ok(Some(self.format.decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn list_snapshots(&self) -> Result<Vec<String>> {
+        self.store.list_snapshots()
+    }
+}
+
+/// File adapter for asynchronous operations, storing data in format `F`
+///
+/// Use an alias such as [`JsonFile`](crate::JsonFile) for a built-in format,
+/// or implement [`Format`] for your own.
+pub struct File<F: Format = Json> {
+    store: FileStore,
+    format: F,
+    recovery: Recovery,
+    last_recovery: Mutex<Option<RecoveryReport>>,
+}
+
+impl<F: Format + Default> File<F> {
+    /// Create a new async file adapter
+    ///
+    /// Writes use [`Durability::Full`] unless configured otherwise. Temp files
+    /// left next to `path` by a crashed writer are removed.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self::with_format(path, F::default())
+    }
+}
+
+impl<F: Format> File<F> {
+    /// Create a new async file adapter using a configured `format`
+    pub fn with_format(path: impl AsRef<Path>, format: F) -> Self {
+        Self {
+            store: FileStore::new(path.as_ref()),
+            format,
+            recovery: Recovery::default(),
+            last_recovery: Mutex::new(None),
+        }
+    }
+
+    /// Set how much effort writes spend making data crash-safe
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.store.set_durability(durability);
+        self
+    }
+
+    /// Keep the previous `count` versions of the file as `<file>.1` (newest)
+    /// through `<file>.<count>` (oldest), rotated on every write
+    pub fn with_backups(mut self, count: usize) -> Self {
+        self.store.set_backups(count);
+        self
+    }
+
+    /// Set how this adapter locks the file against other processes
+    pub fn with_lock(mut self, mode: LockMode) -> Self {
+        self.store.set_lock_mode(mode);
+        self
+    }
+
+    /// Set what happens when the file exists but cannot be decoded
+    pub fn with_recovery(mut self, policy: Recovery) -> Self {
+        self.recovery = policy;
+        self
+    }
+
+    /// Fail writes with [`SaberError::Conflict`](crate::SaberError::Conflict)
+    /// if the file changed since this adapter last read or wrote it
+    ///
+    /// Lets several processes share a file without locks: a writer that lost
+    /// the race gets an error instead of silently overwriting the other's
+    /// change, and can reload and retry. The check happens just before the
+    /// file is replaced, so only a writer finishing in that instant can slip
+    /// through; use [`with_lock`](Self::with_lock) if that matters.
+    pub fn with_version_check(mut self) -> Self {
+        self.store.set_check_version(true);
+        self
+    }
+
+    /// Version of the file as this adapter last read or wrote it
+    ///
+    /// A checksum of the contents, usable as an etag; `None` if the file did
+    /// not exist or has not been read yet.
+    pub fn version(&self) -> Option<String> {
+        self.store.version()
+    }
+
+    /// Report of the most recent recovery from a corrupt file, if any
+    pub fn last_recovery(&self) -> Option<RecoveryReport> {
+        self.last_recovery.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl<T, F> Adapter<T> for File<F>
+where
+    T: Serialize + DeserializeOwned + Send + Sync,
+    F: Format,
+{
+    async fn read(&self) -> Result<Option<T>> {
+        let Some(bytes) = self.store.read_async().await? else {
+            return Ok(None);
+        };
+
+        match self.format.decode(&bytes) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) => {
+                let decode = |bytes: &[u8]| self.format.decode(bytes);
+                let (data, report) =
+                    recovery::recover_async(&self.store, self.recovery, e, decode).await?;
+                *self.last_recovery.lock().unwrap() = Some(report);
+                Ok(data)
+            }
+        }
+    }
+
+    async fn write(&self, data: &T) -> Result<()> {
+        let bytes = self.format.encode(data)?;
+
+        // Atomic write: write to temp file, then rename
+        self.store.write_async(bytes).await
+    }
+
+    async fn read_backup(&self, k: usize) -> Result<Option<T>> {
+        match self.store.read_backup_async(k).await? {
+            Some(bytes) => Ok(Some(self.format.decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn changed(&self) -> Result<bool> {
+        self.store.changed_async().await
+    }
+
+    async fn write_snapshot(&self, name: &str, data: &T) -> Result<()> {
+        let bytes = self.format.encode(data)?;
+        self.store.write_snapshot_async(name, bytes).await
+    }
+
+    async fn read_snapshot(&self, name: &str) -> Result<Option<T>> {
+        match self.store.read_snapshot_async(name).await? {
+            Some(bytes) => Ok(Some(self.format.decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_snapshots(&self) -> Result<Vec<String>> {
+        self.store.list_snapshots_async().await
+    }
+}
@@ -0,0 +1,40 @@
+//! Serialization formats for the file adapters.
+
+use crate::core::Result;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Turns data into bytes and back for a file adapter.
+///
+/// Implement this trait to store data in a format of your own; the file
+/// adapters [`File`](crate::File) and [`FileSync`](crate::FileSync) take
+/// care of atomic writes, backups, locking and recovery.
+///
+/// # Example
+///
+/// ```rust
+/// use saberdb::{FileSync, Format, Result, SaberError};
+/// use serde::{de::DeserializeOwned, Serialize};
+///
+/// /// JSON on a single line
+/// #[derive(Default)]
+/// struct CompactJson;
+///
+/// impl Format for CompactJson {
+///     fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>> {
+///         serde_json::to_vec(data).map_err(|e| SaberError::Serialization(e.into()))
+///     }
+///
+///     fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+///         serde_json::from_slice(bytes).map_err(|e| SaberError::Serialization(e.into()))
+///     }
+/// }
+///
+/// let adapter = FileSync::<CompactJson>::new("db.json");
+/// ```
+pub trait Format: Send + Sync {
+    /// Encode `data` as the complete contents of a file.
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>>;
+
+    /// Decode the complete contents of a file.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T>;
+}
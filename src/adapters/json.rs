@@ -0,0 +1,26 @@
+//! JSON, the default format.
+
+use crate::adapters::file::{File, FileSync};
+use crate::adapters::format::Format;
+use crate::core::Result;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Pretty-printed JSON.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Format for Json {
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(data)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// JSON file adapter for synchronous operations
+pub type JsonFileSync = FileSync<Json>;
+
+/// JSON file adapter for asynchronous operations
+pub type JsonFile = File<Json>;
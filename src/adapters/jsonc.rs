@@ -0,0 +1,276 @@
+//! JSON with comments and trailing commas (JSONC), for hand-edited files.
+
+use crate::adapters::file::{File, FileSync};
+use crate::adapters::format::Format;
+use crate::core::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Mutex;
+
+/// JSON that tolerates `//` and `/* */` comments and trailing commas.
+///
+/// Comments around the top-level keys of an object survive rewrites: those
+/// on the lines above a key and those at the end of its entry are written
+/// back next to the same key, as are comments before and after the document.
+/// Comments nested deeper are dropped. A document without comments is written
+/// as the same pretty-printed JSON as [`Json`](crate::Json).
+#[derive(Debug, Default)]
+pub struct Jsonc {
+    /// Comments of the document last decoded
+    comments: Mutex<Comments>,
+}
+
+impl Format for Jsonc {
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>> {
+        let json = serde_json::to_string_pretty(data)?;
+        Ok(self.comments.lock().unwrap().insert(&json).into_bytes())
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        let (json, comments) = strip(bytes);
+        let data = serde_json::from_slice(&json)?;
+        *self.comments.lock().unwrap() = comments;
+        Ok(data)
+    }
+}
+
+/// JSONC file adapter for synchronous operations
+pub type JsoncFileSync = FileSync<Jsonc>;
+
+/// JSONC file adapter for asynchronous operations
+pub type JsoncFile = File<Jsonc>;
+
+/// Comments around the top level of a document.
+#[derive(Debug, Default)]
+struct Comments {
+    /// Before the document
+    header: Vec<String>,
+    /// Around each top-level key
+    keys: HashMap<String, KeyComments>,
+    /// After the last key, inside the object
+    closing: Vec<String>,
+    /// After the document
+    footer: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct KeyComments {
+    /// On the lines above the key
+    above: Vec<String>,
+    /// At the end of the entry's last line
+    after: Vec<String>,
+}
+
+impl Comments {
+    fn is_empty(&self) -> bool {
+        self.header.is_empty()
+            && self.keys.is_empty()
+            && self.closing.is_empty()
+            && self.footer.is_empty()
+    }
+
+    /// Put the comments back into the pretty-printed `json`.
+    fn insert(&self, json: &str) -> String {
+        if self.is_empty() {
+            return json.to_string();
+        }
+
+        let mut out = String::new();
+        for comment in &self.header {
+            out.push_str(comment);
+            out.push('\n');
+        }
+
+        let lines: Vec<&str> = json.lines().collect();
+        let mut footer = Vec::new();
+        if lines.len() > 2 && lines[0] == "{" {
+            // One member per top-level key line, up to the next one
+            let members = &lines[1..lines.len() - 1];
+            let mut after: &[String] = &[];
+            out.push_str("{\n");
+            for (n, line) in members.iter().enumerate() {
+                if let Some(comments) = top_level_key(line).and_then(|key| self.keys.get(&key)) {
+                    for comment in &comments.above {
+                        out.push_str("  ");
+                        out.push_str(comment);
+                        out.push('\n');
+                    }
+                    after = &comments.after;
+                }
+                out.push_str(line);
+                let member_ends = members
+                    .get(n + 1)
+                    .is_none_or(|next| top_level_key(next).is_some());
+                if member_ends {
+                    for comment in mem::take(&mut after) {
+                        out.push(' ');
+                        out.push_str(comment);
+                    }
+                }
+                out.push('\n');
+            }
+            for comment in &self.closing {
+                out.push_str("  ");
+                out.push_str(comment);
+                out.push('\n');
+            }
+            out.push('}');
+        } else {
+            // Nowhere to put the comments of the members
+            out.push_str(json);
+            footer.extend(&self.closing);
+        }
+
+        footer.extend(&self.footer);
+        for comment in footer {
+            out.push('\n');
+            out.push_str(comment);
+        }
+        out
+    }
+}
+
+/// Blank out the comments and trailing commas of `bytes`, keeping line and
+/// column numbers for error messages, and collect the top-level comments.
+fn strip(bytes: &[u8]) -> (Vec<u8>, Comments) {
+    let mut json = bytes.to_vec();
+    let mut comments = Comments::default();
+
+    let mut depth = 0usize;
+    let mut started = false;
+    let mut in_object = false;
+    let mut expect_key = false;
+    let mut last_key: Option<String> = None;
+    // Comments above the next top-level key
+    let mut pending = Vec::new();
+    // Line of the last byte that is not whitespace or a comment
+    let mut line = 0;
+    let mut last_line = 0;
+    // A comma that is trailing if a closing bracket comes next
+    let mut comma = None;
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => {
+                line += 1;
+                i += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' => {
+                i += 1;
+                continue;
+            }
+            b'/' if matches!(bytes.get(i + 1), Some(b'/' | b'*')) => {
+                let end = comment_end(bytes, i);
+                let text = String::from_utf8_lossy(&bytes[i..end]).trim_end().to_string();
+                if !started {
+                    comments.header.push(text);
+                } else if depth == 0 {
+                    comments.footer.push(text);
+                } else if depth == 1 && in_object {
+                    match &last_key {
+                        Some(key) if line == last_line => {
+                            comments.keys.entry(key.clone()).or_default().after.push(text);
+                        }
+                        _ => pending.push(text),
+                    }
+                }
+
+                for b in &mut json[i..end] {
+                    if *b == b'\n' {
+                        line += 1;
+                    } else {
+                        *b = b' ';
+                    }
+                }
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+
+        let b = bytes[i];
+        if let Some(at) = comma.take()
+            && matches!(b, b'}' | b']')
+        {
+            json[at] = b' ';
+        }
+        let mut end = i + 1;
+        match b {
+            b'"' => {
+                end = string_end(bytes, i);
+                if depth == 1 && in_object && expect_key {
+                    let key: String = serde_json::from_slice(&bytes[i..end]).unwrap_or_default();
+                    if !pending.is_empty() {
+                        comments.keys.entry(key.clone()).or_default().above = mem::take(&mut pending);
+                    }
+                    last_key = Some(key);
+                    expect_key = false;
+                }
+            }
+            b'{' | b'[' => {
+                if !started {
+                    in_object = b == b'{';
+                }
+                depth += 1;
+                expect_key = depth == 1 && in_object;
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && in_object {
+                    comments.closing = mem::take(&mut pending);
+                }
+            }
+            b',' => {
+                comma = Some(i);
+                expect_key = depth == 1 && in_object;
+            }
+            _ => {}
+        }
+
+        started = true;
+        line += bytes[i..end].iter().filter(|&&b| b == b'\n').count();
+        last_line = line;
+        i = end;
+    }
+
+    (json, comments)
+}
+
+/// End of the comment starting at `start`: the end of the line for `//`, just
+/// past the `*/` for `/*`.
+fn comment_end(bytes: &[u8], start: usize) -> usize {
+    let rest = &bytes[start + 2..];
+    let len = if bytes[start + 1] == b'/' {
+        rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len())
+    } else {
+        rest.windows(2)
+            .position(|w| w == b"*/")
+            .map_or(rest.len(), |n| n + 2)
+    };
+    start + 2 + len
+}
+
+/// End of the string literal starting at `start`, just past the closing quote.
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// The key of the top-level member starting on `line` of pretty-printed JSON.
+fn top_level_key(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("  ")?;
+    if !rest.starts_with('"') {
+        return None;
+    }
+    serde_json::from_str(&rest[..string_end(rest.as_bytes(), 0)]).ok()
+}
@@ -0,0 +1,117 @@
+//! Advisory cross-process locking for the file-backed adapters.
+
+use crate::core::{Result, SaberError};
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long to sleep between attempts while waiting for a contended lock.
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How a file adapter coordinates with other processes using the same file.
+///
+/// Locks are advisory and taken on a `<file>.lock` sibling, so they only
+/// protect against other SaberDB instances (or tools that honour the same
+/// lock file), not arbitrary writers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockMode {
+    /// No locking; the last writer wins.
+    #[default]
+    None,
+    /// Hold an exclusive lock from the first read or write until the adapter
+    /// is dropped.
+    ///
+    /// Fails with [`SaberError::Locked`] straight away if another process
+    /// already owns the database.
+    Exclusive,
+    /// Take a shared lock for each read and an exclusive lock for each write.
+    ///
+    /// Waits up to `timeout` for a conflicting lock to be released before
+    /// failing with [`SaberError::Locked`].
+    PerOperation {
+        /// Maximum time to wait for the lock.
+        timeout: Duration,
+    },
+}
+
+/// A lock held for the duration of one operation; released on drop.
+pub(crate) struct LockGuard {
+    _file: Option<File>,
+}
+
+/// Lock state for one database file.
+pub(crate) struct FileLock {
+    mode: LockMode,
+    path: PathBuf,
+    owned: Mutex<Option<File>>,
+}
+
+impl FileLock {
+    pub(crate) fn new(path: &Path, mode: LockMode) -> Self {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+
+        Self {
+            mode,
+            path: path.with_file_name(name),
+            owned: Mutex::new(None),
+        }
+    }
+
+    /// Acquire whatever lock a read needs under the configured mode.
+    pub(crate) fn read(&self) -> Result<LockGuard> {
+        self.acquire(false)
+    }
+
+    /// Acquire whatever lock a write needs under the configured mode.
+    pub(crate) fn write(&self) -> Result<LockGuard> {
+        self.acquire(true)
+    }
+
+    fn acquire(&self, exclusive: bool) -> Result<LockGuard> {
+        match self.mode {
+            LockMode::None => Ok(LockGuard { _file: None }),
+            LockMode::Exclusive => {
+                let mut owned = self.owned.lock().unwrap();
+                if owned.is_none() {
+                    *owned = Some(self.try_acquire(true, Duration::ZERO)?);
+                }
+                Ok(LockGuard { _file: None })
+            }
+            LockMode::PerOperation { timeout } => {
+                let file = self.try_acquire(exclusive, timeout)?;
+                Ok(LockGuard { _file: Some(file) })
+            }
+        }
+    }
+
+    fn try_acquire(&self, exclusive: bool, timeout: Duration) -> Result<File> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)?;
+        let started = Instant::now();
+
+        loop {
+            let attempt = if exclusive {
+                file.try_lock()
+            } else {
+                file.try_lock_shared()
+            };
+
+            match attempt {
+                Ok(()) => return Ok(file),
+                Err(TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(SaberError::Locked(self.path.clone()));
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use async_trait::async_trait;
 use crate::adapters::{Adapter, AdapterSync};
-use crate::core::Result;
+use crate::core::{Result, SaberError};
 use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
 
 /// In-memory adapter for synchronous operations.
@@ -30,6 +31,7 @@ use std::sync::{Arc, RwLock};
 /// ```
 pub struct MemorySync<T> {
     data: Arc<RwLock<Option<T>>>,
+    snapshots: Arc<RwLock<BTreeMap<String, T>>>,
 }
 
 impl<T> MemorySync<T> {
@@ -37,6 +39,7 @@ impl<T> MemorySync<T> {
     pub fn new() -> Self {
         Self {
             data: Arc::new(RwLock::new(None)),
+            snapshots: Arc::new(RwLock::new(BTreeMap::new())),
         }
     }
 }
@@ -51,6 +54,7 @@ impl<T> Clone for MemorySync<T> {
     fn clone(&self) -> Self {
         Self {
             data: Arc::clone(&self.data),
+            snapshots: Arc::clone(&self.snapshots),
         }
     }
 }
@@ -69,6 +73,23 @@ where
         *guard = Some(data.clone());
         Ok(())
     }
+
+    fn write_snapshot(&self, name: &str, data: &T) -> Result<()> {
+        let mut snapshots = self.snapshots.write().unwrap();
+        if snapshots.contains_key(name) {
+            return Err(SaberError::SnapshotExists(name.to_string()));
+        }
+        snapshots.insert(name.to_string(), data.clone());
+        Ok(())
+    }
+
+    fn read_snapshot(&self, name: &str) -> Result<Option<T>> {
+        Ok(self.snapshots.read().unwrap().get(name).cloned())
+    }
+
+    fn list_snapshots(&self) -> Result<Vec<String>> {
+        Ok(self.snapshots.read().unwrap().keys().cloned().collect())
+    }
 }
 
 /// In-memory adapter for asynchronous operations.
@@ -101,6 +122,7 @@ where
 /// ```
 pub struct Memory<T> {
     data: Arc<tokio::sync::RwLock<Option<T>>>,
+    snapshots: Arc<tokio::sync::RwLock<BTreeMap<String, T>>>,
 }
 
 impl<T> Memory<T> {
@@ -108,6 +130,7 @@ impl<T> Memory<T> {
     pub fn new() -> Self {
         Self {
             data: Arc::new(tokio::sync::RwLock::new(None)),
+            snapshots: Arc::new(tokio::sync::RwLock::new(BTreeMap::new())),
         }
     }
 }
@@ -122,6 +145,7 @@ impl<T> Clone for Memory<T> {
     fn clone(&self) -> Self {
         Self {
             data: Arc::clone(&self.data),
+            snapshots: Arc::clone(&self.snapshots),
         }
     }
 }
@@ -141,4 +165,21 @@ where
         *guard = Some(data.clone());
         Ok(())
     }
+
+    async fn write_snapshot(&self, name: &str, data: &T) -> Result<()> {
+        let mut snapshots = self.snapshots.write().await;
+        if snapshots.contains_key(name) {
+            return Err(SaberError::SnapshotExists(name.to_string()));
+        }
+        snapshots.insert(name.to_string(), data.clone());
+        Ok(())
+    }
+
+    async fn read_snapshot(&self, name: &str) -> Result<Option<T>> {
+        Ok(self.snapshots.read().await.get(name).cloned())
+    }
+
+    async fn list_snapshots(&self) -> Result<Vec<String>> {
+        Ok(self.snapshots.read().await.keys().cloned().collect())
+    }
 }
@@ -1,13 +1,49 @@
 //! Storage adapters for different backends.
 
-mod json_file;
+mod atomic;
+mod backup;
+#[cfg(feature = "bincode")]
+mod bincode;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod file;
+mod format;
+mod json;
+mod jsonc;
+mod lock;
 mod memory;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod recovery;
+mod store;
+#[cfg(feature = "toml")]
+mod toml;
+mod wal;
+#[cfg(feature = "yaml")]
+mod yaml;
 
 use async_trait::async_trait;
-use crate::core::Result;
+use crate::core::{Result, SaberError};
 
-pub use json_file::{JsonFileSync, JsonFile};
+pub use atomic::Durability;
+#[cfg(feature = "bincode")]
+pub use bincode::{Bincode, BincodeFile, BincodeFileSync};
+#[cfg(feature = "cbor")]
+pub use cbor::{Cbor, CborFile, CborFileSync};
+pub use file::{File, FileSync};
+pub use format::Format;
+pub use json::{Json, JsonFile, JsonFileSync};
+pub use jsonc::{Jsonc, JsoncFile, JsoncFileSync};
+pub use lock::LockMode;
+pub use recovery::{Recovery, RecoveryAction, RecoveryReport};
+pub use wal::{WalFile, WalFileSync};
 pub use memory::{MemorySync, Memory};
+#[cfg(feature = "msgpack")]
+pub use msgpack::{MessagePack, MessagePackFile, MessagePackFileSync};
+#[cfg(feature = "toml")]
+pub use toml::{Toml, TomlFile, TomlFileSync};
+#[cfg(feature = "yaml")]
+pub use yaml::{Yaml, YamlFile, YamlFileSync};
 
 /// Synchronous adapter trait for storage backends.
 ///
@@ -48,6 +84,45 @@ pub trait AdapterSync<T>: Send + Sync {
     ///
     /// Should be atomic if possible to prevent data corruption.
     fn write(&self, data: &T) -> Result<()>;
+
+    /// Read backup number `k` of the data, where `1` is the most recent.
+    ///
+    /// Returns `Ok(None)` if that backup does not exist. Adapters that keep no
+    /// backups use the default, which fails with [`SaberError::Unsupported`].
+    fn read_backup(&self, k: usize) -> Result<Option<T>> {
+        let _ = k;
+        Err(SaberError::Unsupported("backups"))
+    }
+
+    /// Whether the stored data was changed by someone else since this adapter
+    /// last read or wrote it.
+    ///
+    /// Adapters whose storage cannot change behind their back use the
+    /// default, which always returns `false`.
+    fn changed(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Store `data` as the snapshot called `name`.
+    ///
+    /// Snapshots are immutable: fails with [`SaberError::SnapshotExists`] if
+    /// `name` is taken. Adapters without snapshots use the default, which
+    /// fails with [`SaberError::Unsupported`].
+    fn write_snapshot(&self, name: &str, data: &T) -> Result<()> {
+        let _ = (name, data);
+        Err(SaberError::Unsupported("snapshots"))
+    }
+
+    /// Read the snapshot called `name`, or `Ok(None)` if there is none.
+    fn read_snapshot(&self, name: &str) -> Result<Option<T>> {
+        let _ = name;
+        Err(SaberError::Unsupported("snapshots"))
+    }
+
+    /// Names of all snapshots, sorted.
+    fn list_snapshots(&self) -> Result<Vec<String>> {
+        Err(SaberError::Unsupported("snapshots"))
+    }
 }
 
 /// Asynchronous adapter trait for storage backends.
@@ -92,4 +167,46 @@ pub trait Adapter<T>: Send + Sync {
     ///
     /// Should be atomic if possible to prevent data corruption.
     async fn write(&self, data: &T) -> Result<()>;
+
+    /// Read backup number `k` of the data asynchronously, where `1` is the most recent.
+    ///
+    /// Returns `Ok(None)` if that backup does not exist. Adapters that keep no
+    /// backups use the default, which fails with [`SaberError::Unsupported`].
+    async fn read_backup(&self, k: usize) -> Result<Option<T>> {
+        let _ = k;
+        Err(SaberError::Unsupported("backups"))
+    }
+
+    /// Whether the stored data was changed by someone else since this adapter
+    /// last read or wrote it.
+    ///
+    /// Adapters whose storage cannot change behind their back use the
+    /// default, which always returns `false`.
+    async fn changed(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Store `data` as the snapshot called `name` asynchronously.
+    ///
+    /// Snapshots are immutable: fails with [`SaberError::SnapshotExists`] if
+    /// `name` is taken. Adapters without snapshots use the default, which
+    /// fails with [`SaberError::Unsupported`].
+    async fn write_snapshot(&self, name: &str, data: &T) -> Result<()>
+    where
+        T: Sync,
+    {
+        let _ = (name, data);
+        Err(SaberError::Unsupported("snapshots"))
+    }
+
+    /// Read the snapshot called `name` asynchronously, or `Ok(None)` if there is none.
+    async fn read_snapshot(&self, name: &str) -> Result<Option<T>> {
+        let _ = name;
+        Err(SaberError::Unsupported("snapshots"))
+    }
+
+    /// Names of all snapshots, sorted.
+    async fn list_snapshots(&self) -> Result<Vec<String>> {
+        Err(SaberError::Unsupported("snapshots"))
+    }
 }
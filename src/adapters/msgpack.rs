@@ -0,0 +1,29 @@
+//! MessagePack, a compact binary format.
+
+use crate::adapters::file::{File, FileSync};
+use crate::adapters::format::Format;
+use crate::core::{Result, SaberError};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// MessagePack documents.
+///
+/// Structs are written as maps keyed by field name, so fields can be added
+/// or reordered without breaking existing files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+impl Format for MessagePack {
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>> {
+        rmp_serde::to_vec_named(data).map_err(|e| SaberError::Serialization(e.into()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        rmp_serde::from_slice(bytes).map_err(|e| SaberError::Serialization(e.into()))
+    }
+}
+
+/// MessagePack file adapter for synchronous operations
+pub type MessagePackFileSync = FileSync<MessagePack>;
+
+/// MessagePack file adapter for asynchronous operations
+pub type MessagePackFile = File<MessagePack>;
@@ -0,0 +1,134 @@
+//! Recovery from a database file that exists but cannot be decoded.
+
+use crate::adapters::store::FileStore;
+use crate::core::{Result, SaberError};
+use std::path::PathBuf;
+
+/// What a file adapter does when the database file exists but is corrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Recovery {
+    /// Return the decoding error, leaving the file untouched.
+    #[default]
+    Fail,
+    /// Quarantine the broken file and load the newest backup that decodes,
+    /// copying it back in place of the broken file.
+    ///
+    /// Requires backups to be enabled; fails with the original error if none
+    /// of them is usable.
+    RestoreBackup,
+    /// Quarantine the broken file and start from the default value.
+    Quarantine,
+    /// Start from the default value; the broken file is replaced by the next write.
+    UseDefault,
+}
+
+/// The action taken after a corrupt file was found on read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// Data was loaded from backup number `backup`, which replaced the broken
+    /// file; the broken file was moved to `quarantined`.
+    RestoredBackup {
+        /// Which backup was loaded (1 is the most recent).
+        backup: usize,
+        /// Where the broken file was moved.
+        quarantined: PathBuf,
+    },
+    /// The broken file was moved to `path` and the default value was used.
+    Quarantined {
+        /// Where the broken file was moved.
+        path: PathBuf,
+    },
+    /// The default value was used and the broken file was left in place.
+    UsedDefault,
+}
+
+/// Describes a recovery, so callers can log or surface it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Why the database file could not be read.
+    pub error: String,
+    /// What was done about it.
+    pub action: RecoveryAction,
+}
+
+/// Apply `policy` after `error` was hit decoding the main file.
+pub(crate) fn recover<T>(
+    store: &FileStore,
+    policy: Recovery,
+    error: SaberError,
+    decode: impl Fn(&[u8]) -> Result<T>,
+) -> Result<(Option<T>, RecoveryReport)> {
+    let (data, action) = match policy {
+        Recovery::Fail => return Err(error),
+        Recovery::RestoreBackup => {
+            let Some((backup, bytes, data)) = newest_valid_backup(store, &decode)? else {
+                return Err(error);
+            };
+            let quarantined = store.replace_corrupt(&bytes)?;
+            (Some(data), RecoveryAction::RestoredBackup { backup, quarantined })
+        }
+        Recovery::Quarantine => {
+            let path = store.quarantine()?;
+            (None, RecoveryAction::Quarantined { path })
+        }
+        Recovery::UseDefault => (None, RecoveryAction::UsedDefault),
+    };
+
+    Ok((data, report(error, action)))
+}
+
+/// Async counterpart of [`recover`].
+pub(crate) async fn recover_async<T>(
+    store: &FileStore,
+    policy: Recovery,
+    error: SaberError,
+    decode: impl Fn(&[u8]) -> Result<T>,
+) -> Result<(Option<T>, RecoveryReport)> {
+    let (data, action) = match policy {
+        Recovery::Fail => return Err(error),
+        Recovery::RestoreBackup => {
+            let mut found = None;
+            for k in 1..=store.backups() {
+                if let Some(bytes) = store.read_backup_async(k).await?
+                    && let Ok(data) = decode(&bytes)
+                {
+                    found = Some((k, bytes, data));
+                    break;
+                }
+            }
+            let Some((backup, bytes, data)) = found else {
+                return Err(error);
+            };
+            let quarantined = store.replace_corrupt_async(bytes).await?;
+            (Some(data), RecoveryAction::RestoredBackup { backup, quarantined })
+        }
+        Recovery::Quarantine => {
+            let path = store.quarantine_async().await?;
+            (None, RecoveryAction::Quarantined { path })
+        }
+        Recovery::UseDefault => (None, RecoveryAction::UsedDefault),
+    };
+
+    Ok((data, report(error, action)))
+}
+
+fn newest_valid_backup<T>(
+    store: &FileStore,
+    decode: &impl Fn(&[u8]) -> Result<T>,
+) -> Result<Option<(usize, Vec<u8>, T)>> {
+    for k in 1..=store.backups() {
+        if let Some(bytes) = store.read_backup(k)?
+            && let Ok(data) = decode(&bytes)
+        {
+            return Ok(Some((k, bytes, data)));
+        }
+    }
+    Ok(None)
+}
+
+fn report(error: SaberError, action: RecoveryAction) -> RecoveryReport {
+    RecoveryReport {
+        error: error.to_string(),
+        action,
+    }
+}
@@ -0,0 +1,394 @@
+//! Byte-level file storage shared by the file-backed adapters.
+
+use crate::adapters::atomic::{self, Durability};
+use crate::adapters::backup;
+use crate::adapters::lock::{FileLock, LockMode};
+use crate::core::{Result, SaberError};
+use std::fs::{self, File, Metadata};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Identifies one version of a file without reading it.
+///
+/// Writes replace the file by renaming, which gives it a new inode, so on Unix
+/// even a same-sized change within the filesystem's timestamp granularity is
+/// noticed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    #[cfg(unix)]
+    inode: u64,
+}
+
+impl Stamp {
+    fn of(meta: &Metadata) -> Self {
+        Self {
+            modified: meta.modified().ok(),
+            len: meta.len(),
+            #[cfg(unix)]
+            inode: std::os::unix::fs::MetadataExt::ino(meta),
+        }
+    }
+}
+
+/// The file as a store last read or wrote it.
+#[derive(Debug, Clone, Default)]
+struct Seen {
+    /// `None` if the file was absent
+    stamp: Option<Stamp>,
+    /// [`checksum`] of the contents; `None` if the file was absent
+    version: Option<String>,
+}
+
+impl Seen {
+    fn absent() -> Self {
+        Self::default()
+    }
+
+    fn file(stamp: Stamp, bytes: &[u8]) -> Self {
+        Self {
+            stamp: Some(stamp),
+            version: Some(checksum(bytes)),
+        }
+    }
+}
+
+/// Reads and atomically replaces one file, honouring durability and locking.
+///
+/// Cheap to clone; clones share the same lock state, which lets the async
+/// adapters move a handle onto the blocking thread pool.
+#[derive(Clone)]
+pub(crate) struct FileStore {
+    path: PathBuf,
+    durability: Durability,
+    backups: usize,
+    lock: Arc<FileLock>,
+    /// Refuse to replace a file that changed since it was last seen
+    check_version: bool,
+    seen: Arc<Mutex<Seen>>,
+}
+
+impl FileStore {
+    pub(crate) fn new(path: &Path) -> Self {
+        atomic::remove_stale_temps(path);
+
+        Self {
+            path: path.to_path_buf(),
+            durability: Durability::default(),
+            backups: 0,
+            lock: Arc::new(FileLock::new(path, LockMode::default())),
+            check_version: false,
+            seen: Arc::new(Mutex::new(Seen::absent())),
+        }
+    }
+
+    pub(crate) fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
+
+    pub(crate) fn set_backups(&mut self, count: usize) {
+        self.backups = count;
+    }
+
+    pub(crate) fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    pub(crate) fn backups(&self) -> usize {
+        self.backups
+    }
+
+    pub(crate) fn set_lock_mode(&mut self, mode: LockMode) {
+        self.lock = Arc::new(FileLock::new(&self.path, mode));
+    }
+
+    pub(crate) fn set_check_version(&mut self, check: bool) {
+        self.check_version = check;
+    }
+
+    /// Checksum of the file as last read or written, or `None` if it was absent.
+    pub(crate) fn version(&self) -> Option<String> {
+        self.seen.lock().unwrap().version.clone()
+    }
+
+    /// Read the whole file, or `None` if it does not exist yet.
+    pub(crate) fn read(&self) -> Result<Option<Vec<u8>>> {
+        let _guard = self.lock.read()?;
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                *self.seen.lock().unwrap() = Seen::absent();
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        // Stamp the handle we read from, so a replacement racing the read
+        // still counts as a change
+        let stamp = Stamp::of(&file.metadata()?);
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        *self.seen.lock().unwrap() = Seen::file(stamp, &bytes);
+        Ok(Some(bytes))
+    }
+
+    /// Whether the file differs from how this store last read or wrote it.
+    pub(crate) fn changed(&self) -> Result<bool> {
+        Ok(current_stamp(&self.path)? != self.seen.lock().unwrap().stamp)
+    }
+
+    /// Read backup number `k` (1 is the most recent), or `None` if there is none.
+    pub(crate) fn read_backup(&self, k: usize) -> Result<Option<Vec<u8>>> {
+        if k == 0 || k > self.backups {
+            return Ok(None);
+        }
+        let _guard = self.lock.read()?;
+        Ok(read_if_exists(&backup::path(&self.path, k))?)
+    }
+
+    /// Atomically replace the file with `bytes`, rotating backups first.
+    ///
+    /// With version checking on, fails with [`SaberError::Conflict`] if the
+    /// file changed since it was last read or written.
+    pub(crate) fn write(&self, bytes: &[u8]) -> Result<()> {
+        let _guard = self.lock.write()?;
+        let mut seen = self.seen.lock().unwrap();
+
+        // Checked right before the rename, to keep the window in which
+        // another writer can slip in as small as possible
+        let mut conflict = false;
+        let result = atomic::write(&self.path, bytes, self.durability, || {
+            if self.check_version && !self.unchanged_since(&seen)? {
+                conflict = true;
+                return Err(std::io::Error::other("file changed on disk"));
+            }
+            backup::rotate(&self.path, self.backups)
+        });
+        if conflict {
+            return Err(SaberError::Conflict(self.path.clone()));
+        }
+        result?;
+
+        *seen = Seen::file(Stamp::of(&fs::metadata(&self.path)?), bytes);
+        Ok(())
+    }
+
+    /// Whether the file still has the contents it had when `seen`.
+    fn unchanged_since(&self, seen: &Seen) -> std::io::Result<bool> {
+        let stamp = current_stamp(&self.path)?;
+        if stamp == seen.stamp {
+            return Ok(true);
+        }
+        // Touched, or rewritten by someone; only a change of contents counts
+        Ok(read_if_exists(&self.path)?.map(|bytes| checksum(&bytes)) == seen.version)
+    }
+
+    /// Move the file aside as `<file>.corrupt-<unix millis>` and return the new path.
+    pub(crate) fn quarantine(&self) -> Result<PathBuf> {
+        let _guard = self.lock.write()?;
+        self.move_aside()
+    }
+
+    /// Quarantine the file and put `bytes`, the contents of a good backup, in
+    /// its place.
+    pub(crate) fn replace_corrupt(&self, bytes: &[u8]) -> Result<PathBuf> {
+        let _guard = self.lock.write()?;
+        let target = self.move_aside()?;
+        atomic::write(&self.path, bytes, self.durability, || Ok(()))?;
+        *self.seen.lock().unwrap() = Seen::file(Stamp::of(&fs::metadata(&self.path)?), bytes);
+        Ok(target)
+    }
+
+    /// [`quarantine`](Self::quarantine) with the write lock already held.
+    fn move_aside(&self) -> Result<PathBuf> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".corrupt-{}", millis));
+        let target = self.path.with_file_name(name);
+
+        fs::rename(&self.path, &target)?;
+        *self.seen.lock().unwrap() = Seen::absent();
+        Ok(target)
+    }
+
+    /// Directory holding the snapshots: `<stem>.snapshots` next to the file.
+    fn snapshot_dir(&self) -> PathBuf {
+        let mut name = self.path.file_stem().unwrap_or_default().to_os_string();
+        name.push(".snapshots");
+        self.path.with_file_name(name)
+    }
+
+    /// Path of the snapshot `name`, with the same extension as the file.
+    fn snapshot_path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && !name.contains(['/', '\\', '\0']);
+        if !valid {
+            return Err(SaberError::Adapter(format!(
+                "invalid snapshot name: {:?}",
+                name
+            )));
+        }
+
+        let mut path = self.snapshot_dir().join(name);
+        if let Some(extension) = self.path.extension() {
+            let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+            file_name.push(".");
+            file_name.push(extension);
+            path.set_file_name(file_name);
+        }
+        Ok(path)
+    }
+
+    /// Write `bytes` as the snapshot `name`, which must not exist yet.
+    pub(crate) fn write_snapshot(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.snapshot_path(name)?;
+        fs::create_dir_all(self.snapshot_dir())?;
+
+        let result = atomic::write(&path, bytes, self.durability, || {
+            if path.exists() {
+                return Err(std::io::ErrorKind::AlreadyExists.into());
+            }
+            Ok(())
+        });
+        match result {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(SaberError::SnapshotExists(name.to_string()))
+            }
+            result => Ok(result?),
+        }
+    }
+
+    /// Read the snapshot `name`, or `None` if there is none.
+    pub(crate) fn read_snapshot(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        Ok(read_if_exists(&self.snapshot_path(name)?)?)
+    }
+
+    /// Names of the snapshots, sorted.
+    pub(crate) fn list_snapshots(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(self.snapshot_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let extension = self.path.extension();
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            // Skip temp files of writes in progress
+            if file_name.starts_with('.') {
+                continue;
+            }
+            // Without an extension on the file, a dot in a name is just a dot
+            let name = match extension {
+                Some(extension) if path.extension() == Some(extension) => {
+                    path.file_stem().unwrap_or_default().to_string_lossy()
+                }
+                Some(_) => continue,
+                None => file_name,
+            };
+            names.push(name.into_owned());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Async counterpart of [`read`](Self::read), run on the blocking thread pool.
+    pub(crate) async fn read_async(&self) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        blocking(move || store.read()).await
+    }
+
+    /// Async counterpart of [`read_backup`](Self::read_backup), run on the blocking thread pool.
+    pub(crate) async fn read_backup_async(&self, k: usize) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        blocking(move || store.read_backup(k)).await
+    }
+
+    /// Async counterpart of [`changed`](Self::changed), run on the blocking thread pool.
+    pub(crate) async fn changed_async(&self) -> Result<bool> {
+        let store = self.clone();
+        blocking(move || store.changed()).await
+    }
+
+    /// Async counterpart of [`quarantine`](Self::quarantine), run on the blocking thread pool.
+    pub(crate) async fn quarantine_async(&self) -> Result<PathBuf> {
+        let store = self.clone();
+        blocking(move || store.quarantine()).await
+    }
+
+    /// Async counterpart of [`replace_corrupt`](Self::replace_corrupt), run on the blocking thread pool.
+    pub(crate) async fn replace_corrupt_async(&self, bytes: Vec<u8>) -> Result<PathBuf> {
+        let store = self.clone();
+        blocking(move || store.replace_corrupt(&bytes)).await
+    }
+
+    /// Async counterpart of [`write_snapshot`](Self::write_snapshot), run on the blocking thread pool.
+    pub(crate) async fn write_snapshot_async(&self, name: &str, bytes: Vec<u8>) -> Result<()> {
+        let store = self.clone();
+        let name = name.to_string();
+        blocking(move || store.write_snapshot(&name, &bytes)).await
+    }
+
+    /// Async counterpart of [`read_snapshot`](Self::read_snapshot), run on the blocking thread pool.
+    pub(crate) async fn read_snapshot_async(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        let name = name.to_string();
+        blocking(move || store.read_snapshot(&name)).await
+    }
+
+    /// Async counterpart of [`list_snapshots`](Self::list_snapshots), run on the blocking thread pool.
+    pub(crate) async fn list_snapshots_async(&self) -> Result<Vec<String>> {
+        let store = self.clone();
+        blocking(move || store.list_snapshots()).await
+    }
+
+    /// Async counterpart of [`write`](Self::write), run on the blocking thread pool.
+    pub(crate) async fn write_async(&self, bytes: Vec<u8>) -> Result<()> {
+        let store = self.clone();
+        blocking(move || store.write(&bytes)).await
+    }
+}
+
+fn read_if_exists(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn current_stamp(path: &Path) -> std::io::Result<Option<Stamp>> {
+    match fs::metadata(path) {
+        Ok(meta) => Ok(Some(Stamp::of(&meta))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// FNV-1a hash of `bytes` as hex; stable across processes and Rust versions.
+pub(crate) fn checksum(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Run `f` on the blocking thread pool.
+pub(crate) async fn blocking<F, R>(f: F) -> Result<R>
+where
+    F: FnOnce() -> Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(std::io::Error::other)?
+}
@@ -0,0 +1,106 @@
+//! TOML, for configuration-style documents.
+
+use crate::adapters::file::{File, FileSync};
+use crate::adapters::format::Format;
+use crate::core::{Result, SaberError};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// TOML documents.
+///
+/// TOML is stricter than the data model serde works with, so encoding checks
+/// the data first and fails with [`SaberError::Unrepresentable`] naming the
+/// offending path if the document is not a table, contains a null other than
+/// an absent `Option` field, or an integer outside the signed 64-bit range.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Toml;
+
+impl Format for Toml {
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>> {
+        check_document(&serde_json::to_value(data)?)?;
+        ::toml::to_string_pretty(data)
+            .map(String::into_bytes)
+            .map_err(|e| SaberError::Serialization(e.into()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        let text = std::str::from_utf8(bytes).map_err(|e| SaberError::Serialization(e.into()))?;
+        ::toml::from_str(text).map_err(|e| SaberError::Serialization(e.into()))
+    }
+}
+
+fn check_document(document: &Value) -> Result<()> {
+    let Value::Object(table) = document else {
+        return Err(unrepresentable(format!(
+            "the top level must be a table, not {}",
+            kind(document)
+        )));
+    };
+
+    let mut path = String::new();
+    for (key, value) in table {
+        // Absent optional fields are simply left out
+        if !value.is_null() {
+            check_value(&mut path, key, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_value(path: &mut String, token: &str, value: &Value) -> Result<()> {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+
+    match value {
+        Value::Null => {
+            return Err(unrepresentable(format!("null at {} (TOML has no null)", path)));
+        }
+        Value::Number(n) if n.is_u64() && n.as_i64().is_none() => {
+            return Err(unrepresentable(format!(
+                "integer {} at {} is out of range (TOML integers are signed 64-bit)",
+                n, path
+            )));
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check_value(path, &i.to_string(), item)?;
+            }
+        }
+        Value::Object(table) => {
+            for (key, value) in table {
+                if !value.is_null() {
+                    check_value(path, key, value)?;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    path.truncate(len);
+    Ok(())
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "a table",
+    }
+}
+
+fn unrepresentable(reason: String) -> SaberError {
+    SaberError::Unrepresentable {
+        format: "TOML",
+        reason,
+    }
+}
+
+/// TOML file adapter for synchronous operations
+pub type TomlFileSync = FileSync<Toml>;
+
+/// TOML file adapter for asynchronous operations
+pub type TomlFile = File<Toml>;
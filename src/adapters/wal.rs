@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use crate::adapters::atomic::{self, Durability};
+use crate::adapters::store::{self, checksum, FileStore};
+use crate::adapters::{Adapter, AdapterSync};
+use crate::core::Result;
+use crate::core::patch::{self, PatchOp};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Number of log records appended before the next write compacts the log.
+const DEFAULT_COMPACT_AFTER: usize = 1000;
+
+/// First line of every log: identifies the snapshot the records apply to.
+///
+/// A compaction that crashes after replacing the snapshot but before replacing
+/// the log leaves a log whose checksum no longer matches, so it is ignored
+/// instead of being replayed a second time.
+#[derive(Serialize, Deserialize)]
+struct LogHeader {
+    snapshot: String,
+}
+
+/// Snapshot and log handling shared by the sync and async adapters.
+#[derive(Clone)]
+struct WalCore {
+    store: FileStore,
+    log_path: PathBuf,
+    compact_after: usize,
+    state: Arc<Mutex<WalState>>,
+}
+
+#[derive(Default)]
+struct WalState {
+    /// The document as of the end of the log, once read or written.
+    current: Option<Value>,
+    /// Records in the log since the last compaction.
+    records: usize,
+}
+
+impl WalCore {
+    fn new(path: &Path) -> Self {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".wal");
+        let log_path = path.with_file_name(name);
+        atomic::remove_stale_temps(&log_path);
+
+        Self {
+            store: FileStore::new(path),
+            log_path,
+            compact_after: DEFAULT_COMPACT_AFTER,
+            state: Arc::new(Mutex::new(WalState::default())),
+        }
+    }
+
+    /// Load the snapshot and replay the log on top of it.
+    fn read(&self) -> Result<Option<Value>> {
+        let mut state = self.state.lock().unwrap();
+        *state = WalState::default();
+
+        let Some(snapshot) = self.store.read()? else {
+            return Ok(None);
+        };
+        let mut document: Value = serde_json::from_slice(&snapshot)?;
+        // Without a usable log, leave `current` unset so the next write
+        // compacts and starts a log for this snapshot
+        if let Some(records) = self.replay(&mut document, &checksum(&snapshot))? {
+            state.records = records;
+            state.current = Some(document.clone());
+        }
+
+        Ok(Some(document))
+    }
+
+    /// Apply the log records to `document`, returning how many were applied,
+    /// or `None` if there is no log for this snapshot.
+    ///
+    /// A torn final record, left by a crash mid-append, is cut off the log.
+    fn replay(&self, document: &mut Value, snapshot: &str) -> Result<Option<usize>> {
+        let log = match fs::read_to_string(&self.log_path) {
+            Ok(log) => log,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut lines = log.split_inclusive('\n');
+        let header = lines
+            .next()
+            .and_then(|line| serde_json::from_str::<LogHeader>(line).ok());
+        if header.is_none_or(|h| h.snapshot != snapshot) {
+            return Ok(None);
+        }
+
+        let mut valid_len = log.len() - lines.clone().map(str::len).sum::<usize>();
+        let mut records = 0;
+        for line in lines {
+            if !line.ends_with('\n') {
+                File::options()
+                    .write(true)
+                    .open(&self.log_path)?
+                    .set_len(valid_len as u64)?;
+                break;
+            }
+            let ops: Vec<PatchOp> = serde_json::from_str(line)?;
+            patch::apply(document, &ops)?;
+            valid_len += line.len();
+            records += 1;
+        }
+
+        Ok(Some(records))
+    }
+
+    /// Append the change from the last known document to `document`.
+    fn write(&self, document: Value) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let ops = match &state.current {
+            Some(current) if state.records < self.compact_after => {
+                patch::diff(current, &document)
+            }
+            _ => return self.compact_locked(&mut state, document),
+        };
+        if ops.is_empty() {
+            return Ok(());
+        }
+
+        match self.append(&ops) {
+            Ok(()) => {}
+            // The log went missing; start a fresh one from a full snapshot
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return self.compact_locked(&mut state, document);
+            }
+            // Part of the record may have made it into the log; compact on
+            // the next write rather than append after the torn bytes
+            Err(e) => {
+                state.current = None;
+                return Err(e.into());
+            }
+        }
+        state.current = Some(document);
+        state.records += 1;
+
+        Ok(())
+    }
+
+    fn append(&self, ops: &[PatchOp]) -> io::Result<()> {
+        let mut line = serde_json::to_vec(ops)?;
+        line.push(b'\n');
+
+        let mut log = File::options().append(true).open(&self.log_path)?;
+        log.write_all(&line)?;
+        if self.store.durability() != Durability::None {
+            log.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Fold the log into a new snapshot now.
+    fn compact(&self) -> Result<()> {
+        let current = self.state.lock().unwrap().current.clone();
+        let document = match current {
+            Some(document) => document,
+            None => match self.read()? {
+                Some(document) => document,
+                None => return Ok(()),
+            },
+        };
+
+        let mut state = self.state.lock().unwrap();
+        self.compact_locked(&mut state, document)
+    }
+
+    fn compact_locked(&self, state: &mut WalState, document: Value) -> Result<()> {
+        let snapshot = serde_json::to_vec_pretty(&document)?;
+        self.store.write(&snapshot)?;
+
+        let header = LogHeader {
+            snapshot: checksum(&snapshot),
+        };
+        let mut log = serde_json::to_vec(&header)?;
+        log.push(b'\n');
+        atomic::write(&self.log_path, &log, self.store.durability(), || Ok(()))?;
+
+        state.current = Some(document);
+        state.records = 0;
+        Ok(())
+    }
+}
+
+/// Write-ahead log adapter for synchronous operations
+///
+/// Instead of rewriting the whole document on every write, appends a JSON
+/// Patch of the changes to `<file>.wal`. Reads replay the log on top of the
+/// snapshot in `<file>`, and every so often the log is compacted into a new
+/// snapshot.
+pub struct WalFileSync {
+    core: WalCore,
+}
+
+impl WalFileSync {
+    /// Create a new write-ahead log adapter with its snapshot at `path`
+    ///
+    /// The log is compacted after 1000 records unless configured otherwise.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            core: WalCore::new(path.as_ref()),
+        }
+    }
+
+    /// Set how much effort writes spend making data crash-safe
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.core.store.set_durability(durability);
+        self
+    }
+
+    /// Compact the log into a new snapshot after `records` appended changes
+    pub fn with_compaction(mut self, records: usize) -> Self {
+        self.core.compact_after = records;
+        self
+    }
+
+    /// Fold the log into a new snapshot immediately
+    pub fn compact(&self) -> Result<()> {
+        self.core.compact()
+    }
+}
+
+impl<T> AdapterSync<T> for WalFileSync
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn read(&self) -> Result<Option<T>> {
+        match self.core.read()? {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn write(&self, data: &T) -> Result<()> {
+        self.core.write(serde_json::to_value(data)?)
+    }
+}
+
+/// Write-ahead log adapter for asynchronous operations
+///
+/// See [`WalFileSync`] for the on-disk layout.
+pub struct WalFile {
+    core: WalCore,
+}
+
+impl WalFile {
+    /// Create a new async write-ahead log adapter with its snapshot at `path`
+    ///
+    /// The log is compacted after 1000 records unless configured otherwise.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            core: WalCore::new(path.as_ref()),
+        }
+    }
+
+    /// Set how much effort writes spend making data crash-safe
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.core.store.set_durability(durability);
+        self
+    }
+
+    /// Compact the log into a new snapshot after `records` appended changes
+    pub fn with_compaction(mut self, records: usize) -> Self {
+        self.core.compact_after = records;
+        self
+    }
+
+    /// Fold the log into a new snapshot immediately
+    pub async fn compact(&self) -> Result<()> {
+        let core = self.core.clone();
+        store::blocking(move || core.compact()).await
+    }
+}
+
+#[async_trait]
+impl<T> Adapter<T> for WalFile
+where
+    T: Serialize + DeserializeOwned + Send + Sync,
+{
+    async fn read(&self) -> Result<Option<T>> {
+        let core = self.core.clone();
+        match store::blocking(move || core.read()).await? {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn write(&self, data: &T) -> Result<()> {
+        let value = serde_json::to_value(data)?;
+        let core = self.core.clone();
+        store::blocking(move || core.write(value)).await
+    }
+}
@@ -0,0 +1,28 @@
+//! YAML, for documents people edit by hand.
+
+use crate::adapters::file::{File, FileSync};
+use crate::adapters::format::Format;
+use crate::core::{Result, SaberError};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// YAML documents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Yaml;
+
+impl Format for Yaml {
+    fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>> {
+        serde_yaml_ng::to_string(data)
+            .map(String::into_bytes)
+            .map_err(|e| SaberError::Serialization(e.into()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        serde_yaml_ng::from_slice(bytes).map_err(|e| SaberError::Serialization(e.into()))
+    }
+}
+
+/// YAML file adapter for synchronous operations
+pub type YamlFileSync = FileSync<Yaml>;
+
+/// YAML file adapter for asynchronous operations
+pub type YamlFile = File<Yaml>;
@@ -0,0 +1,94 @@
+//! Background autosave for [`SaberDB`](crate::SaberDB).
+
+use crate::adapters::Adapter;
+use crate::core::db::Shared;
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+
+/// Handle to a running autosave task.
+///
+/// Dropping the handle asks the task to make a final save and exit, but
+/// nothing waits for it; [`stop`](Self::stop) does.
+pub(crate) struct AutosaveTask {
+    shutdown: Arc<Notify>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl AutosaveTask {
+    pub(crate) fn spawn<T, A>(
+        shared: Arc<Shared<T, A>>,
+        quiet: Duration,
+        max_interval: Duration,
+    ) -> Self
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + Clone + 'static,
+        A: Adapter<T> + 'static,
+    {
+        let shutdown = Arc::new(Notify::new());
+        let task = tokio::spawn(run(shared, quiet, max_interval, Arc::clone(&shutdown)));
+
+        Self {
+            shutdown,
+            task: Some(task),
+        }
+    }
+
+    /// Ask the task to make its final save and wait for it to exit.
+    pub(crate) async fn stop(mut self) {
+        self.shutdown.notify_one();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for AutosaveTask {
+    fn drop(&mut self) {
+        self.shutdown.notify_one();
+    }
+}
+
+/// Wait for a change, debounce until things are quiet (or `max_interval` has
+/// passed since the change), save, repeat.
+async fn run<T, A>(
+    shared: Arc<Shared<T, A>>,
+    quiet: Duration,
+    max_interval: Duration,
+    shutdown: Arc<Notify>,
+) where
+    T: Serialize + DeserializeOwned + Send + Sync + Clone,
+    A: Adapter<T>,
+{
+    loop {
+        tokio::select! {
+            _ = shared.changed.notified() => {}
+            _ = shutdown.notified() => break,
+        }
+
+        let deadline = Instant::now() + max_interval;
+        loop {
+            let wake = (Instant::now() + quiet).min(deadline);
+            tokio::select! {
+                _ = shared.changed.notified() => {
+                    if Instant::now() >= deadline {
+                        break;
+                    }
+                }
+                _ = time::sleep_until(wake) => break,
+                _ = shutdown.notified() => {
+                    let _ = shared.flush().await;
+                    return;
+                }
+            }
+        }
+
+        // Errors leave the data dirty; the next change or flush retries
+        let _ = shared.flush().await;
+    }
+
+    let _ = shared.flush().await;
+}
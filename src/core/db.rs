@@ -1,8 +1,42 @@
 use crate::adapters::{Adapter, AdapterSync};
-use crate::core::Result;
+use crate::core::autosave::AutosaveTask;
+use crate::core::events::{ChangeEvent, Events, PathEvent};
+use crate::core::guard::{DataMutGuard, EditGuard};
+use crate::core::history::History;
+use crate::core::watch::WatchTask;
+use crate::core::{Result, SaberError};
 use serde::{de::DeserializeOwned, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock as AsyncRwLock;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{broadcast, Mutex as AsyncMutex, Notify, RwLock as AsyncRwLock};
+
+/// What a [`SaberDBSync`] does if it is dropped with unsaved changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnDrop {
+    /// Discard the changes silently.
+    #[default]
+    Ignore,
+    /// Write the changes; failures are reported on stderr.
+    Flush,
+    /// Report the lost changes on stderr.
+    Warn,
+    /// Panic in debug builds, to catch a forgotten `write` early; behaves
+    /// like [`Warn`](Self::Warn) in release builds or while already panicking.
+    Panic,
+}
+
+/// What a reload does when the storage changed but the in-memory data has
+/// unsaved changes of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Keep the in-memory data; the next write overwrites the external change.
+    #[default]
+    KeepMemory,
+    /// Replace the in-memory data, losing its unsaved changes.
+    Reload,
+}
 
 /// Synchronous database
 pub struct SaberDBSync<T, A>
@@ -12,6 +46,14 @@ where
 {
     adapter: Arc<A>,
     data: T,
+    /// Whether the data may differ from what is in storage
+    dirty: AtomicBool,
+    /// Whether the data was changed through this instance since it was last
+    /// written; unlike `dirty`, not set for a default that was never stored
+    modified: AtomicBool,
+    last_written_at: Mutex<Option<SystemTime>>,
+    on_drop: OnDrop,
+    history: History,
 }
 
 impl<T, A> SaberDBSync<T, A>
@@ -24,30 +66,94 @@ where
     /// If the adapter can read existing data, it will be loaded.
     /// Otherwise, the default value is used.
     pub fn new(adapter: A, default: T) -> Result<Self> {
-        let data = match adapter.read()? {
-            Some(d) => d,
-            None => default,
+        // A default that has never been stored counts as an unsaved change
+        let (data, dirty) = match adapter.read()? {
+            Some(d) => (d, false),
+            None => (default, true),
         };
 
         Ok(Self {
             adapter: Arc::new(adapter),
             data,
+            dirty: AtomicBool::new(dirty),
+            modified: AtomicBool::new(false),
+            last_written_at: Mutex::new(None),
+            on_drop: OnDrop::default(),
+            history: History::default(),
         })
     }
 
+    /// Set what happens if the database is dropped with unsaved changes
+    ///
+    /// Only changes made through this instance count; a default that was never
+    /// stored does not.
+    pub fn with_on_drop(mut self, policy: OnDrop) -> Self {
+        self.on_drop = policy;
+        self
+    }
+
+    /// Keep the states before the last `limit` updates for [`undo`](Self::undo)
+    ///
+    /// Changes made through [`update`](Self::update),
+    /// [`try_update`](Self::try_update) and
+    /// [`restore_backup`](Self::restore_backup) are recorded; changes made
+    /// directly through [`data_mut`](Self::data_mut) are not.
+    pub fn with_history(mut self, limit: usize) -> Self {
+        self.history = History::new(limit);
+        self
+    }
+
+    /// Get reference to the adapter
+    pub fn adapter(&self) -> &A {
+        &self.adapter
+    }
+
     /// Get immutable reference to the data
     pub fn data(&self) -> &T {
         &self.data
     }
 
+    /// Get a guard over the data that writes it when dropped
+    ///
+    /// Use [`DataMutGuard::commit`] instead of dropping to see write errors.
+    pub fn data_mut_guard(&mut self) -> DataMutGuard<'_, T, A> {
+        DataMutGuard::new(self)
+    }
+
     /// Get mutable reference to the data
+    ///
+    /// Marks the data dirty, whether or not it is actually changed.
     pub fn data_mut(&mut self) -> &mut T {
+        *self.dirty.get_mut() = true;
+        *self.modified.get_mut() = true;
         &mut self.data
     }
 
+    /// Whether the data may differ from what is in storage
+    ///
+    /// True after the data was touched since it was last read or written, and
+    /// for a default that has not been stored yet.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::SeqCst)
+    }
+
+    /// When this instance last wrote to storage, if ever
+    pub fn last_written_at(&self) -> Option<SystemTime> {
+        *self.last_written_at.lock().unwrap()
+    }
+
     /// Write current data to storage
+    ///
+    /// Skipped if the data is not [dirty](Self::is_dirty).
     pub fn write(&self) -> Result<()> {
-        self.adapter.write(&self.data)
+        if !self.is_dirty() {
+            return Ok(());
+        }
+        self.adapter.write(&self.data)?;
+        self.dirty.store(false, Ordering::SeqCst);
+        self.modified.store(false, Ordering::SeqCst);
+        *self.last_written_at.lock().unwrap() = Some(SystemTime::now());
+        Ok(())
     }
 
     /// Update the data and write to storage atomically
@@ -55,19 +161,233 @@ where
     where
         F: FnOnce(&mut T),
     {
-        f(&mut self.data);
+        let before = self.before_change()?;
+        f(self.data_mut());
+        self.record(before);
+        self.write()
+    }
+
+    /// Undo the last recorded update and write the restored data
+    ///
+    /// Returns `false` if there is nothing to undo. See
+    /// [`with_history`](Self::with_history).
+    pub fn undo(&mut self) -> Result<bool> {
+        self.step(History::undo)
+    }
+
+    /// Redo the last undone update and write the restored data
+    ///
+    /// Returns `false` if there is nothing to redo. Any new update clears
+    /// the steps that could be redone.
+    pub fn redo(&mut self) -> Result<bool> {
+        self.step(History::redo)
+    }
+
+    /// Number of updates that can be undone
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Move through the history with `step` and write the state it lands on
+    fn step(&mut self, step: fn(&mut History, Value) -> Option<Value>) -> Result<bool> {
+        let current = serde_json::to_value(&self.data)?;
+        let Some(state) = step(&mut self.history, current) else {
+            return Ok(false);
+        };
+        *self.data_mut() = serde_json::from_value(state)?;
+        self.write()?;
+        Ok(true)
+    }
+
+    /// The data as it is before a change, if history is being recorded
+    fn before_change(&self) -> Result<Option<Value>> {
+        if !self.history.enabled() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::to_value(&self.data)?))
+    }
+
+    fn record(&mut self, before: Option<Value>) {
+        if let Some(before) = before {
+            self.history.record(before);
+        }
+    }
+
+    /// Save the current data as a named, immutable snapshot
+    ///
+    /// Fails with [`SaberError::SnapshotExists`] if `name` is taken, or
+    /// [`SaberError::Unsupported`] if the adapter keeps no snapshots.
+    pub fn snapshot(&self, name: &str) -> Result<()> {
+        self.adapter.write_snapshot(name, &self.data)
+    }
+
+    /// Names of the saved snapshots, sorted
+    pub fn list_snapshots(&self) -> Result<Vec<String>> {
+        self.adapter.list_snapshots()
+    }
+
+    /// Replace the data with the snapshot called `name` and write it
+    ///
+    /// The snapshot itself is left in place, so it can be restored again.
+    pub fn restore(&mut self, name: &str) -> Result<()> {
+        let snapshot = self
+            .adapter
+            .read_snapshot(name)?
+            .ok_or_else(|| SaberError::SnapshotNotFound(name.to_string()))?;
+        let before = self.before_change()?;
+        *self.data_mut() = snapshot;
+        self.record(before);
+        self.write()
+    }
+
+    /// Replace the data with what is currently in storage
+    ///
+    /// Unsaved changes are lost. If the storage no longer exists, the data is
+    /// kept and marked dirty so the next write recreates it.
+    pub fn reload(&mut self) -> Result<()> {
+        match self.adapter.read()? {
+            Some(data) => {
+                self.data = data;
+                *self.dirty.get_mut() = false;
+                *self.modified.get_mut() = false;
+                self.history.clear();
+            }
+            None => *self.dirty.get_mut() = true,
+        }
+        Ok(())
+    }
+
+    /// Reload if the storage was changed by someone else, returning whether it was
+    ///
+    /// `policy` decides what happens when there are also unsaved changes.
+    pub fn reload_if_changed(&mut self, policy: ConflictPolicy) -> Result<bool> {
+        if !self.adapter.changed()? {
+            return Ok(false);
+        }
+        if self.is_dirty() && policy == ConflictPolicy::KeepMemory {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// Replace the data with backup number `k` (1 is the most recent) and write it
+    ///
+    /// The state being replaced is itself rotated into the backups by the write,
+    /// so a restore can be undone by restoring backup 1.
+    pub fn restore_backup(&mut self, k: usize) -> Result<()> {
+        let backup = self
+            .adapter
+            .read_backup(k)?
+            .ok_or(SaberError::BackupNotFound(k))?;
+        let before = self.before_change()?;
+        *self.data_mut() = backup;
+        self.record(before);
         self.write()
     }
 }
 
+impl<T, A> SaberDBSync<T, A>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    A: AdapterSync<T>,
+{
+    /// Update the data with a fallible closure and write to storage
+    ///
+    /// Returns the closure's value once the write succeeds. If the closure
+    /// returns an error or the write fails, the data is rolled back to its
+    /// state before the call, so memory and storage stay consistent.
+    pub fn try_update<F, R, E>(&mut self, f: F) -> std::result::Result<R, E>
+    where
+        F: FnOnce(&mut T) -> std::result::Result<R, E>,
+        E: From<SaberError>,
+    {
+        let snapshot = self.data.clone();
+        let was_dirty = self.is_dirty();
+        let was_modified = *self.modified.get_mut();
+        let before = self.before_change()?;
+
+        let result = f(self.data_mut()).and_then(|value| {
+            self.write()?;
+            Ok(value)
+        });
+
+        match result {
+            Ok(_) => self.record(before),
+            Err(_) => {
+                self.data = snapshot;
+                *self.dirty.get_mut() = was_dirty;
+                *self.modified.get_mut() = was_modified;
+            }
+        }
+        result
+    }
+}
+
+impl<T, A> Drop for SaberDBSync<T, A>
+where
+    T: Serialize + DeserializeOwned,
+    A: AdapterSync<T>,
+{
+    fn drop(&mut self) {
+        if !*self.modified.get_mut() {
+            return;
+        }
+
+        match self.on_drop {
+            OnDrop::Ignore => {}
+            OnDrop::Flush => {
+                if let Err(e) = self.write() {
+                    eprintln!("saberdb: failed to write unsaved changes on drop: {}", e);
+                }
+            }
+            OnDrop::Panic if cfg!(debug_assertions) && !std::thread::panicking() => {
+                panic!("saberdb: database dropped with unsaved changes");
+            }
+            OnDrop::Warn | OnDrop::Panic => {
+                eprintln!("saberdb: database dropped with unsaved changes");
+            }
+        }
+    }
+}
+
 /// Asynchronous database
+///
+/// Writes snapshot the data under the lock and perform I/O after releasing it,
+/// so readers and writers are never blocked on disk. Every mutation gets a
+/// revision number, and a snapshot is never written over a newer one, so the
+/// storage always moves forward in mutation order.
+///
+/// In group-commit mode (see [`with_group_commit`](Self::with_group_commit))
+/// concurrent writers share a single write instead of each doing their own.
 pub struct SaberDB<T, A>
 where
     T: Serialize + DeserializeOwned + Send + Sync,
     A: Adapter<T>,
 {
-    adapter: Arc<A>,
-    data: Arc<AsyncRwLock<T>>,
+    pub(crate) shared: Arc<Shared<T, A>>,
+    group_commit: bool,
+    autosave: Option<AutosaveTask>,
+    watch: Option<WatchTask>,
+}
+
+/// State shared between a [`SaberDB`] and its background tasks
+pub(crate) struct Shared<T, A> {
+    adapter: A,
+    data: AsyncRwLock<T>,
+    /// Revision of the in-memory data, bumped under the write lock
+    revision: AtomicU64,
+    /// Revision last written to storage
+    persisted: AtomicU64,
+    /// Held for the duration of each write, so writes never overlap
+    io: AsyncMutex<()>,
+    last_written_at: Mutex<Option<SystemTime>>,
+    /// Signalled on every mutation
+    pub(crate) changed: Notify,
+    /// Told about every write
+    events: Events,
+    /// Only touched under the data write lock
+    history: Mutex<History>,
 }
 
 impl<T, A> SaberDB<T, A>
@@ -80,42 +400,528 @@ where
     /// If the adapter can read existing data, it will be loaded.
     /// Otherwise, the default value is used.
     pub async fn new(adapter: A, default: T) -> Result<Self> {
-        let data = match adapter.read().await? {
-            Some(d) => d,
-            None => default,
+        // A default that has never been stored counts as an unsaved change
+        let (data, revision, stored) = match adapter.read().await? {
+            Some(d) => {
+                let stored = serde_json::to_value(&d).ok();
+                (d, 0, stored)
+            }
+            None => (default, 1, None),
         };
 
         Ok(Self {
-            adapter: Arc::new(adapter),
-            data: Arc::new(AsyncRwLock::new(data)),
+            shared: Arc::new(Shared {
+                adapter,
+                data: AsyncRwLock::new(data),
+                revision: AtomicU64::new(revision),
+                persisted: AtomicU64::new(0),
+                io: AsyncMutex::new(()),
+                last_written_at: Mutex::new(None),
+                changed: Notify::new(),
+                events: Events::new(stored),
+                history: Mutex::new(History::default()),
+            }),
+            group_commit: false,
+            autosave: None,
+            watch: None,
         })
     }
 
+    /// Enable group commit
+    ///
+    /// Instead of each write storing the snapshot taken when it was issued,
+    /// the writer that gets the storage next stores the latest data, covering
+    /// every change made so far. Writers whose change was covered return as
+    /// soon as that write completes, so a burst of concurrent updates costs a
+    /// couple of writes rather than one each.
+    pub fn with_group_commit(mut self) -> Self {
+        self.group_commit = true;
+        self
+    }
+
+    /// Save changes in the background
+    ///
+    /// Spawns a task that writes the data once it has been left unchanged for
+    /// `quiet`, or at the latest `max_interval` after the first unsaved
+    /// change, so mutations made through [`data_mut`](Self::data_mut) are not
+    /// lost if [`write`](Self::write) is forgotten. Failed saves are retried on
+    /// the next change; call [`flush`](Self::flush) to save now and see errors.
+    ///
+    /// Call [`close`](Self::close) on shutdown to stop the task and make a
+    /// final save. Dropping the database only asks the task for that save,
+    /// which is lost if the runtime shuts down first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn with_autosave(mut self, quiet: Duration, max_interval: Duration) -> Self
+    where
+        T: 'static,
+        A: 'static,
+    {
+        self.autosave = Some(AutosaveTask::spawn(
+            Arc::clone(&self.shared),
+            quiet,
+            max_interval,
+        ));
+        self
+    }
+
+    /// Reload the data whenever the storage is changed by someone else
+    ///
+    /// Spawns a task that asks the adapter every `interval` whether the
+    /// storage changed since this database last read or wrote it (see
+    /// [`Adapter::changed`]) and if so reloads it. `policy` decides what
+    /// happens when there are also unsaved changes. Reads that fail, such as
+    /// of a file another tool is halfway through writing, are retried on the
+    /// next tick.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn with_watch(mut self, interval: Duration, policy: ConflictPolicy) -> Self
+    where
+        T: 'static,
+        A: 'static,
+    {
+        self.watch = Some(WatchTask::spawn(
+            Arc::clone(&self.shared),
+            interval,
+            policy,
+        ));
+        self
+    }
+
+    /// Keep the states before the last `limit` updates for [`undo`](Self::undo)
+    ///
+    /// Changes made through [`update`](Self::update),
+    /// [`update_async`](Self::update_async), [`try_update`](Self::try_update),
+    /// [`edit`](Self::edit) and [`restore_backup`](Self::restore_backup) are
+    /// recorded; changes made directly through [`data_mut`](Self::data_mut)
+    /// are not. A [reload](Self::reload) clears the history.
+    pub fn with_history(self, limit: usize) -> Self {
+        *self.shared.history.lock().unwrap() = History::new(limit);
+        self
+    }
+
+    /// Attach a JSON Patch of what changed to every [`ChangeEvent`]
+    ///
+    /// Costs a diff of the whole document on every write.
+    pub fn with_diffs(self) -> Self {
+        // Nothing else can hold the write lock while the database is being built
+        let base = self
+            .shared
+            .data
+            .try_read()
+            .ok()
+            .and_then(|data| serde_json::to_value(&*data).ok());
+        self.shared.events.enable_diffs(base);
+        self
+    }
+
+    /// Get reference to the adapter
+    pub fn adapter(&self) -> &A {
+        &self.shared.adapter
+    }
+
+    /// Receive a [`ChangeEvent`] each time new data reaches storage
+    ///
+    /// Events follow successful writes, whether from [`update`](Self::update),
+    /// [`write`](Self::write), autosave or a [reload](Self::reload), in
+    /// revision order. With group commit, one event covers every change the
+    /// write included. A subscriber that falls more than 64 events behind
+    /// gets [`RecvError::Lagged`](broadcast::error::RecvError::Lagged) and
+    /// resumes from the oldest event still buffered.
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.shared.events.subscribe()
+    }
+
+    /// Receive a [`PathEvent`] each time the value at a JSON Pointer changes in storage
+    ///
+    /// `pointer` is an RFC 6901 pointer such as `/settings/theme`, or `""` for
+    /// the whole document. Writes that leave the value unchanged send nothing;
+    /// the first event compares against the value in storage when watching
+    /// began, not against unsaved changes in memory. Buffers 64 events per
+    /// pointer, as [`subscribe`](Self::subscribe) does.
+    ///
+    /// Fails with [`SaberError::InvalidPointer`] if `pointer` is not a valid
+    /// JSON Pointer.
+    pub async fn watch_path(
+        &self,
+        pointer: impl Into<String>,
+    ) -> Result<broadcast::Receiver<PathEvent>> {
+        let pointer = pointer.into();
+        // Non-empty pointers start with `/`, and `~` only escapes `~0` or `~1`
+        let escapes_valid = pointer
+            .split('~')
+            .skip(1)
+            .all(|rest| rest.starts_with(['0', '1']));
+        if !(pointer.is_empty() || pointer.starts_with('/')) || !escapes_valid {
+            return Err(SaberError::InvalidPointer(pointer));
+        }
+
+        // No write can publish between taking the stored value and registering
+        let _io = self.shared.io.lock().await;
+        Ok(self.shared.events.watch_path(pointer))
+    }
+
     /// Get immutable reference to the data
     pub async fn data(&self) -> tokio::sync::RwLockReadGuard<'_, T> {
-        self.data.read().await
+        self.shared.data.read().await
     }
 
     /// Get mutable reference to the data
+    ///
+    /// Marks the data dirty, whether or not it is actually changed.
     pub async fn data_mut(&self) -> tokio::sync::RwLockWriteGuard<'_, T> {
-        self.data.write().await
+        let data = self.shared.data.write().await;
+        self.shared.bump();
+        data
+    }
+
+    /// Lock the data for an edit that is either committed or discarded
+    ///
+    /// The returned guard derefs to the data. [`EditGuard::commit`] writes the
+    /// changes and releases the lock; [`EditGuard::discard`], or dropping the
+    /// guard, puts the data back as it was.
+    pub async fn edit(&self) -> EditGuard<'_, T, A> {
+        EditGuard::new(self, self.shared.data.write().await)
+    }
+
+    /// Whether the data may differ from what is in storage
+    ///
+    /// True after the data was touched since it was last read or written, and
+    /// for a default that has not been stored yet.
+    pub fn is_dirty(&self) -> bool {
+        self.shared.revision() > self.shared.persisted.load(Ordering::SeqCst)
+    }
+
+    /// When this instance last wrote to storage, if ever
+    pub fn last_written_at(&self) -> Option<SystemTime> {
+        *self.shared.last_written_at.lock().unwrap()
     }
 
     /// Write current data to storage
+    ///
+    /// The data is cloned under the read lock, which is released before any
+    /// I/O. Skipped if the data is not [dirty](Self::is_dirty).
     pub async fn write(&self) -> Result<()> {
-        let data = self.data.read().await;
-        self.adapter.write(&*data).await
+        let (snapshot, revision) = {
+            let data = self.shared.data.read().await;
+            (self.copy_for_write(&data), self.shared.revision())
+        };
+        self.shared.persist(snapshot, revision).await
+    }
+
+    /// Write any changes not yet in storage
+    ///
+    /// Returns immediately if everything is already saved.
+    pub async fn flush(&self) -> Result<()> {
+        self.shared.flush().await
+    }
+
+    /// Stop background tasks and save any pending changes
+    pub async fn close(mut self) -> Result<()> {
+        if let Some(watch) = self.watch.take() {
+            watch.stop().await;
+        }
+        if let Some(autosave) = self.autosave.take() {
+            autosave.stop().await;
+        }
+        self.flush().await
     }
 
     /// Update the data and write to storage atomically
+    ///
+    /// The written snapshot is taken before the write lock is released, so it
+    /// contains exactly this update and no later change from another task.
     pub async fn update<F>(&self, f: F) -> Result<()>
     where
         F: FnOnce(&mut T),
     {
-        {
-            let mut data = self.data.write().await;
+        let (snapshot, revision) = {
+            let mut data = self.shared.data.write().await;
+            let before = self.shared.before_change(&data)?;
             f(&mut data);
+            self.shared.record(before);
+            (self.copy_for_write(&data), self.shared.bump())
+        };
+        self.shared.persist(snapshot, revision).await
+    }
+
+    /// Undo the last recorded update and write the restored data
+    ///
+    /// Returns `false` if there is nothing to undo. See
+    /// [`with_history`](Self::with_history).
+    pub async fn undo(&self) -> Result<bool> {
+        self.step(History::undo).await
+    }
+
+    /// Redo the last undone update and write the restored data
+    ///
+    /// Returns `false` if there is nothing to redo. Any new update clears
+    /// the steps that could be redone.
+    pub async fn redo(&self) -> Result<bool> {
+        self.step(History::redo).await
+    }
+
+    /// Number of updates that can be undone
+    pub fn history_len(&self) -> usize {
+        self.shared.history.lock().unwrap().len()
+    }
+
+    /// Move through the history with `step` and write the state it lands on
+    async fn step(&self, step: fn(&mut History, Value) -> Option<Value>) -> Result<bool> {
+        let (snapshot, revision) = {
+            let mut data = self.shared.data.write().await;
+            let current = serde_json::to_value(&*data)?;
+            let Some(state) = step(&mut self.shared.history.lock().unwrap(), current) else {
+                return Ok(false);
+            };
+            *data = serde_json::from_value(state)?;
+            (self.copy_for_write(&data), self.shared.bump())
+        };
+        self.shared.persist(snapshot, revision).await?;
+        Ok(true)
+    }
+
+    /// Update the data with an async closure and write to storage
+    ///
+    /// The write lock is held while the closure's future runs, so it can await
+    /// other work (another service, another database) and its changes still
+    /// appear atomic to other tasks. Returns the closure's value.
+    pub async fn update_async<F, R>(&self, f: F) -> Result<R>
+    where
+        F: AsyncFnOnce(&mut T) -> R,
+    {
+        let (value, snapshot, revision) = {
+            let mut data = self.shared.data.write().await;
+            let before = self.shared.before_change(&data)?;
+            let value = f(&mut data).await;
+            self.shared.record(before);
+            (value, self.copy_for_write(&data), self.shared.bump())
+        };
+        self.shared.persist(snapshot, revision).await?;
+        Ok(value)
+    }
+
+    /// Update the data with a fallible closure and write to storage
+    ///
+    /// Returns the closure's value once the write succeeds. If the closure
+    /// returns an error or the write fails, the data is rolled back to its
+    /// state before the call. The write lock is held until the outcome is
+    /// known, so other tasks never observe a change that is later undone.
+    pub async fn try_update<F, R, E>(&self, f: F) -> std::result::Result<R, E>
+    where
+        F: FnOnce(&mut T) -> std::result::Result<R, E>,
+        E: From<SaberError>,
+    {
+        // Storage before data, the same order group commit takes them in
+        let _io = self.shared.io.lock().await;
+        let mut data = self.shared.data.write().await;
+        let snapshot = data.clone();
+        let before = self.shared.before_change(&data)?;
+
+        let result = match f(&mut data) {
+            Ok(value) => {
+                let revision = self.shared.bump();
+                match self.shared.adapter.write(&*data).await {
+                    Ok(()) => {
+                        self.shared.mark_written(revision, &*data);
+                        self.shared.record(before);
+                        Ok(value)
+                    }
+                    Err(e) => {
+                        // Nothing else can have bumped it while we hold the lock
+                        self.shared.revision.store(revision - 1, Ordering::SeqCst);
+                        Err(e.into())
+                    }
+                }
+            }
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() {
+            *data = snapshot;
         }
-        self.write().await
+        result
+    }
+
+    /// Save the current data as a named, immutable snapshot
+    ///
+    /// The data is cloned under the read lock and written after releasing
+    /// it. Fails with [`SaberError::SnapshotExists`] if `name` is taken, or
+    /// [`SaberError::Unsupported`] if the adapter keeps no snapshots.
+    pub async fn snapshot(&self, name: &str) -> Result<()> {
+        let data = self.shared.data.read().await.clone();
+        self.shared.adapter.write_snapshot(name, &data).await
+    }
+
+    /// Names of the saved snapshots, sorted
+    pub async fn list_snapshots(&self) -> Result<Vec<String>> {
+        self.shared.adapter.list_snapshots().await
+    }
+
+    /// Replace the data with the snapshot called `name` and write it
+    ///
+    /// The snapshot itself is left in place, so it can be restored again.
+    pub async fn restore(&self, name: &str) -> Result<()> {
+        let snapshot = self
+            .shared
+            .adapter
+            .read_snapshot(name)
+            .await?
+            .ok_or_else(|| SaberError::SnapshotNotFound(name.to_string()))?;
+        let (copy, revision) = {
+            let mut data = self.shared.data.write().await;
+            let before = self.shared.before_change(&data)?;
+            *data = snapshot;
+            self.shared.record(before);
+            (self.copy_for_write(&data), self.shared.bump())
+        };
+        self.shared.persist(copy, revision).await
+    }
+
+    /// Replace the data with what is currently in storage
+    ///
+    /// Unsaved changes are lost. If the storage no longer exists, the data is
+    /// kept and marked dirty so the next write recreates it.
+    pub async fn reload(&self) -> Result<()> {
+        let _io = self.shared.io.lock().await;
+        self.shared.reload_locked().await
+    }
+
+    /// Reload if the storage was changed by someone else, returning whether it was
+    ///
+    /// `policy` decides what happens when there are also unsaved changes.
+    pub async fn reload_if_changed(&self, policy: ConflictPolicy) -> Result<bool> {
+        self.shared.reload_if_changed(policy).await
+    }
+
+    /// Replace the data with backup number `k` (1 is the most recent) and write it
+    ///
+    /// The state being replaced is itself rotated into the backups by the write,
+    /// so a restore can be undone by restoring backup 1.
+    pub async fn restore_backup(&self, k: usize) -> Result<()> {
+        let backup = self
+            .shared
+            .adapter
+            .read_backup(k)
+            .await?
+            .ok_or(SaberError::BackupNotFound(k))?;
+        let (snapshot, revision) = {
+            let mut data = self.shared.data.write().await;
+            let before = self.shared.before_change(&data)?;
+            *data = backup;
+            self.shared.record(before);
+            (self.copy_for_write(&data), self.shared.bump())
+        };
+        self.shared.persist(snapshot, revision).await
+    }
+
+    /// Clone the data for a later write; group commit defers this to the write itself
+    pub(crate) fn copy_for_write(&self, data: &T) -> Option<T> {
+        (!self.group_commit).then(|| data.clone())
+    }
+}
+
+impl<T, A> Shared<T, A>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + Clone,
+    A: Adapter<T>,
+{
+    fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    /// Record a mutation made under the write lock, returning its revision
+    pub(crate) fn bump(&self) -> u64 {
+        let revision = self.revision.fetch_add(1, Ordering::SeqCst) + 1;
+        self.changed.notify_one();
+        revision
+    }
+
+    /// Write the latest data if it has changes not yet in storage
+    pub(crate) async fn flush(&self) -> Result<()> {
+        self.persist(None, self.revision()).await
+    }
+
+    /// Make the change at `revision` durable
+    ///
+    /// Returns at once if a write already covered `revision`. Otherwise writes
+    /// `snapshot`, or without one (group commit) the latest data on behalf of
+    /// every waiter.
+    pub(crate) async fn persist(&self, snapshot: Option<T>, revision: u64) -> Result<()> {
+        let _io = self.io.lock().await;
+        if self.persisted.load(Ordering::SeqCst) >= revision {
+            return Ok(());
+        }
+
+        match snapshot {
+            Some(snapshot) => {
+                self.adapter.write(&snapshot).await?;
+                self.mark_written(revision, &snapshot);
+            }
+            None => {
+                let (latest, latest_revision) = {
+                    let data = self.data.read().await;
+                    (data.clone(), self.revision())
+                };
+                self.adapter.write(&latest).await?;
+                self.mark_written(latest_revision, &latest);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reload if the storage changed, unless `policy` keeps pending changes
+    pub(crate) async fn reload_if_changed(&self, policy: ConflictPolicy) -> Result<bool> {
+        // Holding the storage means none of our own writes is in flight
+        let _io = self.io.lock().await;
+        if !self.adapter.changed().await? {
+            return Ok(false);
+        }
+        let dirty = self.revision() > self.persisted.load(Ordering::SeqCst);
+        if dirty && policy == ConflictPolicy::KeepMemory {
+            return Ok(false);
+        }
+        self.reload_locked().await?;
+        Ok(true)
+    }
+
+    /// Replace the data with the stored data; the caller holds `io`
+    async fn reload_locked(&self) -> Result<()> {
+        let stored = self.adapter.read().await?;
+        let mut data = self.data.write().await;
+        let revision = self.bump();
+        if let Some(stored) = stored {
+            *data = stored;
+            self.history.lock().unwrap().clear();
+            self.persisted.store(revision, Ordering::SeqCst);
+            self.events.publish(revision, &*data);
+        }
+        Ok(())
+    }
+
+    /// The data as it is before a change, if history is being recorded
+    pub(crate) fn before_change(&self, data: &T) -> Result<Option<Value>> {
+        if !self.history.lock().unwrap().enabled() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::to_value(data)?))
+    }
+
+    pub(crate) fn record(&self, before: Option<Value>) {
+        if let Some(before) = before {
+            self.history.lock().unwrap().record(before);
+        }
+    }
+
+    /// Record that `data` at `revision` reached storage; the caller holds `io`
+    fn mark_written(&self, revision: u64, data: &T) {
+        self.persisted.store(revision, Ordering::SeqCst);
+        *self.last_written_at.lock().unwrap() = Some(SystemTime::now());
+        self.events.publish(revision, data);
     }
 }
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,10 +7,43 @@ pub enum SaberError {
     Io(#[from] std::io::Error),
 
     #[error("Serialization error: {0}")]
-    Serialization(#[from] serde_json::Error),
+    Serialization(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Cannot store data as {format}: {reason}")]
+    Unrepresentable {
+        format: &'static str,
+        reason: String,
+    },
 
     #[error("Adapter error: {0}")]
     Adapter(String),
+
+    #[error("Invalid JSON Pointer: {0:?}")]
+    InvalidPointer(String),
+
+    #[error("Database locked by another process: {}", .0.display())]
+    Locked(PathBuf),
+
+    #[error("Database changed on disk since it was last read: {}", .0.display())]
+    Conflict(PathBuf),
+
+    #[error("Backup not found: {0}")]
+    BackupNotFound(usize),
+
+    #[error("Snapshot not found: {0}")]
+    SnapshotNotFound(String),
+
+    #[error("Snapshot already exists: {0}")]
+    SnapshotExists(String),
+
+    #[error("Operation not supported by this adapter: {0}")]
+    Unsupported(&'static str),
+}
+
+impl From<serde_json::Error> for SaberError {
+    fn from(e: serde_json::Error) -> Self {
+        SaberError::Serialization(Box::new(e))
+    }
 }
 
 pub type Result<T> = std::result::Result<T, SaberError>;
@@ -0,0 +1,146 @@
+//! Change notifications for [`SaberDB`](crate::SaberDB).
+
+use crate::core::patch::{self, PatchOp};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::broadcast;
+
+/// Events a subscriber can fall behind by before it starts missing them.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Sent to subscribers each time new data reaches storage.
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    /// Revision of the data now in storage; increases with every change.
+    pub revision: u64,
+    /// JSON Patch from the previous event's data to this one's.
+    ///
+    /// `None` unless diffs were enabled with
+    /// [`with_diffs`](crate::SaberDB::with_diffs).
+    pub diff: Option<Arc<[PatchOp]>>,
+}
+
+/// Sent to a path watcher when the value at its JSON Pointer changes in storage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathEvent {
+    /// The watched JSON Pointer.
+    pub pointer: String,
+    /// The value before the change; `None` if the path did not exist.
+    pub old: Option<Value>,
+    /// The value after the change; `None` if the path no longer exists.
+    pub new: Option<Value>,
+    /// Revision of the data now in storage.
+    pub revision: u64,
+}
+
+/// Watchers of one JSON Pointer.
+struct PathWatch {
+    sender: broadcast::Sender<PathEvent>,
+    /// The value at the pointer as of the last event
+    last: Option<Value>,
+}
+
+/// Publishes [`ChangeEvent`]s and [`PathEvent`]s to subscribers.
+pub(crate) struct Events {
+    sender: broadcast::Sender<ChangeEvent>,
+    diffs: AtomicBool,
+    /// The data as of the last event, for diffing
+    base: Mutex<Option<Value>>,
+    /// The document in storage as of the last event; `None` if nothing is
+    /// stored yet
+    stored: Mutex<Option<Value>>,
+    paths: Mutex<HashMap<String, PathWatch>>,
+}
+
+impl Events {
+    /// Events for a database whose storage currently holds `stored`.
+    pub(crate) fn new(stored: Option<Value>) -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+            diffs: AtomicBool::new(false),
+            base: Mutex::new(None),
+            stored: Mutex::new(stored),
+            paths: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Start attaching diffs to events, starting from `base`.
+    pub(crate) fn enable_diffs(&self, base: Option<Value>) {
+        *self.base.lock().unwrap() = base;
+        self.diffs.store(true, Ordering::SeqCst);
+    }
+
+    /// Watch the value at `pointer` for changes from what is in storage now.
+    ///
+    /// Watchers of the same pointer share one channel, whose last value is
+    /// kept in step with the stored document by [`publish`](Self::publish).
+    pub(crate) fn watch_path(&self, pointer: String) -> broadcast::Receiver<PathEvent> {
+        let mut paths = self.paths.lock().unwrap();
+        match paths.get(&pointer) {
+            Some(watch) if watch.sender.receiver_count() > 0 => watch.sender.subscribe(),
+            _ => {
+                let last = self
+                    .stored
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .and_then(|stored| stored.pointer(&pointer).cloned());
+                let (sender, receiver) = broadcast::channel(CHANNEL_CAPACITY);
+                paths.insert(pointer, PathWatch { sender, last });
+                receiver
+            }
+        }
+    }
+
+    /// Announce that `data` at `revision` is now in storage.
+    ///
+    /// Callers hold the storage lock, so events go out in revision order.
+    pub(crate) fn publish<T: Serialize>(&self, revision: u64, data: &T) {
+        let diffs = self.diffs.load(Ordering::SeqCst);
+        let mut paths = self.paths.lock().unwrap();
+        paths.retain(|_, watch| watch.sender.receiver_count() > 0);
+
+        // Kept even without watchers, so a new one starts from what is stored
+        let new = serde_json::to_value(data).ok();
+
+        if let Some(new) = &new {
+            for (pointer, watch) in paths.iter_mut() {
+                let value = new.pointer(pointer).cloned();
+                if value != watch.last {
+                    let old = std::mem::replace(&mut watch.last, value.clone());
+                    let _ = watch.sender.send(PathEvent {
+                        pointer: pointer.clone(),
+                        old,
+                        new: value,
+                        revision,
+                    });
+                }
+            }
+        }
+        drop(paths);
+
+        let diff = if diffs {
+            let mut base = self.base.lock().unwrap();
+            let diff = match (base.as_ref(), new.as_ref()) {
+                (Some(old), Some(new)) => Some(Arc::from(patch::diff(old, new))),
+                _ => None,
+            };
+            *base = new.clone();
+            diff
+        } else {
+            None
+        };
+        *self.stored.lock().unwrap() = new;
+
+        // Fails only when nobody is subscribed
+        let _ = self.sender.send(ChangeEvent { revision, diff });
+    }
+}
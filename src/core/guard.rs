@@ -0,0 +1,218 @@
+//! Scoped guards that persist changes when they end.
+
+use crate::adapters::{Adapter, AdapterSync};
+use crate::core::{Result, SaberDB, SaberDBSync};
+use serde::{de::DeserializeOwned, Serialize};
+use std::ops::{Deref, DerefMut};
+use tokio::sync::RwLockWriteGuard;
+
+/// Mutable access to a [`SaberDBSync`]'s data that writes it when dropped.
+///
+/// Returned by [`SaberDBSync::data_mut_guard`]. Dropping the guard writes
+/// any changes and reports a failure on stderr; call
+/// [`commit`](Self::commit) to handle the error instead.
+///
+/// # Example
+///
+/// ```rust
+/// use saberdb::{MemorySync, SaberDBSync};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// struct Data {
+///     value: u32,
+/// }
+///
+/// # fn main() -> saberdb::Result<()> {
+/// let mut db = SaberDBSync::new(MemorySync::new(), Data::default())?;
+///
+/// {
+///     let mut data = db.data_mut_guard();
+///     data.value = 42;
+/// } // written here
+///
+/// let mut data = db.data_mut_guard();
+/// data.value += 1;
+/// data.commit()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DataMutGuard<'a, T, A>
+where
+    T: Serialize + DeserializeOwned,
+    A: AdapterSync<T>,
+{
+    db: &'a mut SaberDBSync<T, A>,
+    committed: bool,
+}
+
+impl<'a, T, A> DataMutGuard<'a, T, A>
+where
+    T: Serialize + DeserializeOwned,
+    A: AdapterSync<T>,
+{
+    pub(crate) fn new(db: &'a mut SaberDBSync<T, A>) -> Self {
+        Self {
+            db,
+            committed: false,
+        }
+    }
+
+    /// Write the changes now and end the guard
+    pub fn commit(mut self) -> Result<()> {
+        self.committed = true;
+        self.db.write()
+    }
+}
+
+impl<T, A> Deref for DataMutGuard<'_, T, A>
+where
+    T: Serialize + DeserializeOwned,
+    A: AdapterSync<T>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.db.data()
+    }
+}
+
+impl<T, A> DerefMut for DataMutGuard<'_, T, A>
+where
+    T: Serialize + DeserializeOwned,
+    A: AdapterSync<T>,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.db.data_mut()
+    }
+}
+
+impl<T, A> Drop for DataMutGuard<'_, T, A>
+where
+    T: Serialize + DeserializeOwned,
+    A: AdapterSync<T>,
+{
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        if let Err(e) = self.db.write() {
+            eprintln!("saberdb: failed to write changes when guard dropped: {}", e);
+        }
+    }
+}
+
+/// An edit of a [`SaberDB`]'s data that is either committed or discarded.
+///
+/// Returned by [`SaberDB::edit`]. Holds the write lock for its whole life, so
+/// other tasks see either none of the edit or all of it. Dropping the guard
+/// without calling [`commit`](Self::commit) discards the edit.
+///
+/// # Example
+///
+/// ```rust
+/// use saberdb::{Memory, SaberDB};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// struct Data {
+///     value: u32,
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> saberdb::Result<()> {
+/// let db = SaberDB::new(Memory::new(), Data::default()).await?;
+///
+/// let mut data = db.edit().await;
+/// data.value = 42;
+/// data.commit().await?;
+///
+/// let mut data = db.edit().await;
+/// data.value = 0;
+/// data.discard();
+/// assert_eq!(db.data().await.value, 42);
+/// # Ok(())
+/// # }
+/// ```
+pub struct EditGuard<'a, T, A>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + Clone,
+    A: Adapter<T>,
+{
+    db: &'a SaberDB<T, A>,
+    /// `None` once committed
+    data: Option<RwLockWriteGuard<'a, T>>,
+    /// The data as it was when the edit began
+    original: Option<T>,
+}
+
+impl<'a, T, A> EditGuard<'a, T, A>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + Clone,
+    A: Adapter<T>,
+{
+    pub(crate) fn new(db: &'a SaberDB<T, A>, data: RwLockWriteGuard<'a, T>) -> Self {
+        let original = Some(data.clone());
+        Self {
+            db,
+            data: Some(data),
+            original,
+        }
+    }
+
+    /// Keep the edit, release the lock and write the data to storage
+    ///
+    /// As with [`SaberDB::update`], a failed write leaves the edit in memory
+    /// and the data [dirty](SaberDB::is_dirty).
+    pub async fn commit(mut self) -> Result<()> {
+        // On error the guard is dropped and the edit discarded
+        let before = match &self.original {
+            Some(original) => self.db.shared.before_change(original)?,
+            None => None,
+        };
+        let (snapshot, revision) = {
+            let data = self.data.take().expect("edit guard already committed");
+            self.original = None;
+            self.db.shared.record(before);
+            (self.db.copy_for_write(&data), self.db.shared.bump())
+        };
+        self.db.shared.persist(snapshot, revision).await
+    }
+
+    /// Undo the edit and release the lock
+    pub fn discard(self) {}
+}
+
+impl<T, A> Deref for EditGuard<'_, T, A>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + Clone,
+    A: Adapter<T>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.data.as_ref().expect("edit guard already committed")
+    }
+}
+
+impl<T, A> DerefMut for EditGuard<'_, T, A>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + Clone,
+    A: Adapter<T>,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.data.as_mut().expect("edit guard already committed")
+    }
+}
+
+impl<T, A> Drop for EditGuard<'_, T, A>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + Clone,
+    A: Adapter<T>,
+{
+    fn drop(&mut self) {
+        if let (Some(data), Some(original)) = (self.data.as_mut(), self.original.take()) {
+            **data = original;
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Bounded undo/redo history of document states.
+
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Prior states for undo and undone states for redo.
+///
+/// States are kept as JSON values, so the data type needs no `Clone`.
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    /// Maximum number of undo steps; 0 disables recording
+    limit: usize,
+    undo: VecDeque<Value>,
+    redo: Vec<Value>,
+}
+
+impl History {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.limit > 0
+    }
+
+    /// Number of steps that can be undone.
+    pub(crate) fn len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Record the state from before a change; the redo steps no longer apply.
+    pub(crate) fn record(&mut self, before: Value) {
+        if !self.enabled() {
+            return;
+        }
+        self.push_undo(before);
+        self.redo.clear();
+    }
+
+    /// Step back from `current`, returning the state to restore.
+    pub(crate) fn undo(&mut self, current: Value) -> Option<Value> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// Step forward from `current`, returning the state to restore.
+    pub(crate) fn redo(&mut self, current: Value) -> Option<Value> {
+        let next = self.redo.pop()?;
+        self.push_undo(current);
+        Some(next)
+    }
+
+    /// Forget every step, e.g. after the data was replaced from storage.
+    pub(crate) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push_undo(&mut self, state: Value) {
+        self.undo.push_back(state);
+        if self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+}
@@ -1,5 +1,14 @@
+mod autosave;
 mod error;
 mod db;
+mod events;
+mod guard;
+mod history;
+mod watch;
+pub(crate) mod patch;
 
 pub use error::{SaberError, Result};
-pub use db::{SaberDB, SaberDBSync};
+pub use db::{ConflictPolicy, OnDrop, SaberDB, SaberDBSync};
+pub use events::{ChangeEvent, PathEvent};
+pub use guard::{DataMutGuard, EditGuard};
+pub use patch::PatchOp;
@@ -0,0 +1,157 @@
+//! Minimal JSON Patch (RFC 6902) diffing and application.
+
+use crate::core::{Result, SaberError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One JSON Patch operation; `path` is a JSON Pointer into the document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    /// Insert `value` at `path` (appending with `-` for arrays).
+    Add { path: String, value: Value },
+    /// Remove the value at `path`.
+    Remove { path: String },
+    /// Replace the value at `path` with `value`.
+    Replace { path: String, value: Value },
+}
+
+/// Compute the operations that turn `old` into `new`.
+///
+/// Objects are compared key by key and arrays index by index, with trailing
+/// elements added or removed; anything else that differs is replaced whole.
+pub fn diff(old: &Value, new: &Value) -> Vec<PatchOp> {
+    let mut ops = Vec::new();
+    diff_into(&mut ops, &mut String::new(), old, new);
+    ops
+}
+
+fn diff_into(ops: &mut Vec<PatchOp>, path: &mut String, old: &Value, new: &Value) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, old_value) in a {
+                let len = push_token(path, key);
+                match b.get(key) {
+                    Some(new_value) => diff_into(ops, path, old_value, new_value),
+                    None => ops.push(PatchOp::Remove { path: path.clone() }),
+                }
+                path.truncate(len);
+            }
+            for (key, new_value) in b {
+                if !a.contains_key(key) {
+                    let len = push_token(path, key);
+                    ops.push(PatchOp::Add {
+                        path: path.clone(),
+                        value: new_value.clone(),
+                    });
+                    path.truncate(len);
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, (old_value, new_value)) in a.iter().zip(b).enumerate() {
+                let len = push_token(path, &i.to_string());
+                diff_into(ops, path, old_value, new_value);
+                path.truncate(len);
+            }
+            // Remove from the end so earlier indices stay valid
+            for i in (b.len()..a.len()).rev() {
+                let len = push_token(path, &i.to_string());
+                ops.push(PatchOp::Remove { path: path.clone() });
+                path.truncate(len);
+            }
+            for value in b.iter().skip(a.len()) {
+                let len = push_token(path, "-");
+                ops.push(PatchOp::Add {
+                    path: path.clone(),
+                    value: value.clone(),
+                });
+                path.truncate(len);
+            }
+        }
+        _ if old != new => ops.push(PatchOp::Replace {
+            path: path.clone(),
+            value: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Append an escaped reference token to `path`, returning the previous length.
+fn push_token(path: &mut String, token: &str) -> usize {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    len
+}
+
+/// Apply `ops` to `target` in order.
+pub fn apply(target: &mut Value, ops: &[PatchOp]) -> Result<()> {
+    for op in ops {
+        apply_one(target, op)?;
+    }
+    Ok(())
+}
+
+fn apply_one(target: &mut Value, op: &PatchOp) -> Result<()> {
+    let (path, value) = match op {
+        PatchOp::Add { path, value } | PatchOp::Replace { path, value } => (path, Some(value)),
+        PatchOp::Remove { path } => (path, None),
+    };
+
+    let Some((parent_path, token)) = split_last(path) else {
+        // The root itself
+        return match value {
+            Some(value) => {
+                *target = value.clone();
+                Ok(())
+            }
+            None => Err(invalid(path)),
+        };
+    };
+    let parent = target.pointer_mut(parent_path).ok_or_else(|| invalid(path))?;
+
+    match (parent, op) {
+        (Value::Object(map), PatchOp::Add { value, .. }) => {
+            map.insert(token, value.clone());
+        }
+        (Value::Object(map), PatchOp::Replace { value, .. }) => {
+            *map.get_mut(&token).ok_or_else(|| invalid(path))? = value.clone();
+        }
+        (Value::Object(map), PatchOp::Remove { .. }) => {
+            map.remove(&token).ok_or_else(|| invalid(path))?;
+        }
+        (Value::Array(items), PatchOp::Add { value, .. }) if token == "-" => {
+            items.push(value.clone());
+        }
+        (Value::Array(items), op) => {
+            let index: usize = token.parse().map_err(|_| invalid(path))?;
+            match op {
+                PatchOp::Add { value, .. } if index <= items.len() => {
+                    items.insert(index, value.clone());
+                }
+                PatchOp::Replace { value, .. } if index < items.len() => {
+                    items[index] = value.clone();
+                }
+                PatchOp::Remove { .. } if index < items.len() => {
+                    items.remove(index);
+                }
+                _ => return Err(invalid(path)),
+            }
+        }
+        _ => return Err(invalid(path)),
+    }
+
+    Ok(())
+}
+
+/// Split a pointer into its parent pointer and unescaped last token.
+fn split_last(path: &str) -> Option<(&str, String)> {
+    let index = path.rfind('/')?;
+    let token = path[index + 1..].replace("~1", "/").replace("~0", "~");
+    Some((&path[..index], token))
+}
+
+fn invalid(path: &str) -> SaberError {
+    SaberError::Adapter(format!("invalid patch path: {:?}", path))
+}
@@ -0,0 +1,76 @@
+//! Background reloading of external changes for [`SaberDB`](crate::SaberDB).
+
+use crate::adapters::Adapter;
+use crate::core::db::{ConflictPolicy, Shared};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+
+/// Handle to a running watch task.
+///
+/// Dropping the handle asks the task to exit.
+pub(crate) struct WatchTask {
+    shutdown: Arc<Notify>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl WatchTask {
+    pub(crate) fn spawn<T, A>(
+        shared: Arc<Shared<T, A>>,
+        interval: Duration,
+        policy: ConflictPolicy,
+    ) -> Self
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + Clone + 'static,
+        A: Adapter<T> + 'static,
+    {
+        let shutdown = Arc::new(Notify::new());
+        let task = tokio::spawn(run(shared, interval, policy, Arc::clone(&shutdown)));
+
+        Self {
+            shutdown,
+            task: Some(task),
+        }
+    }
+
+    /// Ask the task to exit and wait for it.
+    pub(crate) async fn stop(mut self) {
+        self.shutdown.notify_one();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for WatchTask {
+    fn drop(&mut self) {
+        self.shutdown.notify_one();
+    }
+}
+
+/// Poll the adapter for external changes every `interval` until shut down.
+async fn run<T, A>(
+    shared: Arc<Shared<T, A>>,
+    interval: Duration,
+    policy: ConflictPolicy,
+    shutdown: Arc<Notify>,
+) where
+    T: Serialize + DeserializeOwned + Send + Sync + Clone,
+    A: Adapter<T>,
+{
+    let mut ticks = time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = shutdown.notified() => break,
+        }
+
+        // Errors, e.g. from a half-written file, are retried on the next tick
+        let _ = shared.reload_if_changed(policy).await;
+    }
+}
@@ -7,7 +7,7 @@
 //! - **Simple API** - Direct data manipulation, no query language needed
 //! - **Type-safe** - Full Rust type safety with generics
 //! - **Sync & Async** - Both synchronous and asynchronous APIs
-//! - **Atomic writes** - Crash-safe with atomic file operations
+//! - **Atomic writes** - Crash-safe with atomic, fsync'd file operations
 //! - **Thread-safe** - True concurrent reads with async version
 //!
 //! ## Quick Start
@@ -61,5 +61,22 @@
 pub mod adapters;
 pub mod core;
 
-pub use crate::core::{SaberDB, SaberDBSync, Result};
-pub use crate::adapters::{Adapter, AdapterSync, JsonFile, JsonFileSync, Memory, MemorySync};
+pub use crate::core::{
+    ChangeEvent, ConflictPolicy, DataMutGuard, EditGuard, OnDrop, PatchOp, PathEvent, SaberDB,
+    SaberDBSync, SaberError, Result,
+};
+pub use crate::adapters::{
+    Adapter, AdapterSync, Durability, File, FileSync, Format, Json, JsonFile, JsonFileSync, Jsonc,
+    JsoncFile, JsoncFileSync, LockMode, Memory, MemorySync, Recovery, RecoveryAction,
+    RecoveryReport, WalFile, WalFileSync,
+};
+#[cfg(feature = "bincode")]
+pub use crate::adapters::{Bincode, BincodeFile, BincodeFileSync};
+#[cfg(feature = "cbor")]
+pub use crate::adapters::{Cbor, CborFile, CborFileSync};
+#[cfg(feature = "msgpack")]
+pub use crate::adapters::{MessagePack, MessagePackFile, MessagePackFileSync};
+#[cfg(feature = "toml")]
+pub use crate::adapters::{Toml, TomlFile, TomlFileSync};
+#[cfg(feature = "yaml")]
+pub use crate::adapters::{Yaml, YamlFile, YamlFileSync};
@@ -1,6 +1,14 @@
-use saberdb::{JsonFile, SaberDB};
+use async_trait::async_trait;
+use saberdb::{
+    Adapter, ConflictPolicy, Durability, JsonFile, LockMode, Memory, PatchOp, Recovery,
+    RecoveryAction, SaberDB, SaberError, WalFile,
+};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Notify, Semaphore};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 struct TestData {
@@ -20,6 +28,19 @@ impl Default for TestData {
 fn cleanup(path: &str) {
     let _ = fs::remove_file(path);
     let _ = fs::remove_file(format!("{}.tmp", path));
+    let _ = fs::remove_file(format!("{}.lock", path));
+    let _ = fs::remove_file(format!("{}.wal", path));
+    let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+    let _ = fs::remove_dir_all(format!("{}.snapshots", stem));
+    for k in 1..=5 {
+        let _ = fs::remove_file(format!("{}.{}", path, k));
+    }
+    let corrupt = format!("{}.corrupt-", path);
+    for entry in fs::read_dir(".").unwrap().filter_map(|e| e.ok()) {
+        if entry.file_name().to_string_lossy().starts_with(&corrupt) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
 }
 
 #[tokio::test]
@@ -158,3 +179,930 @@ async fn test_async_persistence_across_instances() {
 
     cleanup(path);
 }
+
+#[tokio::test]
+async fn test_async_durability_levels_round_trip() {
+    for (path, durability) in [
+        ("test_async_durability_none.json", Durability::None),
+        ("test_async_durability_data.json", Durability::Data),
+        ("test_async_durability_full.json", Durability::Full),
+    ] {
+        cleanup(path);
+
+        let adapter = JsonFile::new(path).with_durability(durability);
+        let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+        db.update(|data| data.counter = 7).await.unwrap();
+
+        let adapter = JsonFile::new(path);
+        let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+        assert_eq!(db.data().await.counter, 7);
+
+        cleanup(path);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_async_concurrent_writes_do_not_collide() {
+    let path = "test_async_concurrent_writes.json";
+    cleanup(path);
+
+    let adapter = JsonFile::new(path).with_durability(Durability::None);
+    let db = Arc::new(SaberDB::new(adapter, TestData::default()).await.unwrap());
+
+    let tasks: Vec<_> = (0..16)
+        .map(|i| {
+            let db = Arc::clone(&db);
+            tokio::spawn(async move { db.update(move |data| data.counter = i).await })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    let prefix = format!(".{}.", path);
+    let leftovers = fs::read_dir(".")
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|n| n.starts_with(&prefix) && n.ends_with(".tmp"))
+        .count();
+    assert_eq!(leftovers, 0);
+
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_exclusive_lock_rejects_second_owner() {
+    let path = "test_async_lock_exclusive.json";
+    cleanup(path);
+
+    let adapter = JsonFile::new(path).with_lock(LockMode::Exclusive);
+    let owner = SaberDB::new(adapter, TestData::default()).await.unwrap();
+    owner.update(|data| data.counter = 1).await.unwrap();
+
+    let adapter = JsonFile::new(path).with_lock(LockMode::Exclusive);
+    let result = SaberDB::new(adapter, TestData::default()).await;
+    assert!(matches!(result, Err(SaberError::Locked(_))));
+
+    drop(owner);
+    let adapter = JsonFile::new(path).with_lock(LockMode::Exclusive);
+    let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+    assert_eq!(db.data().await.counter, 1);
+
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_restore_backup() {
+    let path = "test_async_backups.json";
+    cleanup(path);
+
+    let adapter = JsonFile::new(path).with_backups(3);
+    let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+    db.update(|data| data.counter = 1).await.unwrap();
+    db.update(|data| data.counter = 2).await.unwrap();
+    db.update(|data| data.counter = 3).await.unwrap();
+
+    db.restore_backup(2).await.unwrap();
+    assert_eq!(db.data().await.counter, 1);
+
+    let db2 = SaberDB::new(JsonFile::new(path), TestData::default()).await.unwrap();
+    assert_eq!(db2.data().await.counter, 1);
+
+    assert!(matches!(db.restore_backup(4).await, Err(SaberError::BackupNotFound(4))));
+
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_corrupt_file_restored_from_backup() {
+    let path = "test_async_corrupt_backup.json";
+    cleanup(path);
+
+    {
+        let adapter = JsonFile::new(path).with_backups(1);
+        let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+        db.update(|data| data.counter = 1).await.unwrap();
+        db.update(|data| data.counter = 2).await.unwrap();
+    }
+    fs::write(path, "{").unwrap();
+
+    let adapter = JsonFile::new(path)
+        .with_backups(1)
+        .with_recovery(Recovery::RestoreBackup);
+    let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+    assert_eq!(db.data().await.counter, 1);
+
+    let report = db.adapter().last_recovery().unwrap();
+    let RecoveryAction::RestoredBackup { backup, quarantined } = report.action else {
+        panic!("unexpected action: {:?}", report.action);
+    };
+    assert_eq!(backup, 1);
+    assert_eq!(fs::read_to_string(quarantined).unwrap(), "{");
+    let reopened = SaberDB::new(JsonFile::new(path), TestData::default()).await.unwrap();
+    assert_eq!(reopened.data().await.counter, 1);
+
+    // Without a usable backup the original error is returned
+    cleanup(path);
+    fs::write(path, "{").unwrap();
+    let adapter = JsonFile::new(path).with_recovery(Recovery::RestoreBackup);
+    let result = SaberDB::new(adapter, TestData::default()).await;
+    assert!(matches!(result, Err(SaberError::Serialization(_))));
+
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_wal_round_trip() {
+    let path = "test_async_wal.json";
+    cleanup(path);
+
+    {
+        let adapter = WalFile::new(path).with_compaction(10);
+        let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+        for i in 1..=4 {
+            db.update(|data| data.counter = i).await.unwrap();
+        }
+        db.update(|data| data.message = "logged".to_string()).await.unwrap();
+    }
+
+    let db = SaberDB::new(WalFile::new(path), TestData::default()).await.unwrap();
+    assert_eq!(db.data().await.counter, 4);
+    assert_eq!(db.data().await.message, "logged");
+
+    db.adapter().compact().await.unwrap();
+    let snapshot: TestData = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+    assert_eq!(snapshot.message, "logged");
+
+    cleanup(path);
+}
+
+/// In-memory adapter whose writes fail while `fail` is set
+struct FlakyAdapter {
+    inner: Memory<TestData>,
+    fail: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl Adapter<TestData> for FlakyAdapter {
+    async fn read(&self) -> saberdb::Result<Option<TestData>> {
+        self.inner.read().await
+    }
+
+    async fn write(&self, data: &TestData) -> saberdb::Result<()> {
+        if self.fail.load(Ordering::SeqCst) {
+            return Err(SaberError::Adapter("disk full".to_string()));
+        }
+        self.inner.write(data).await
+    }
+}
+
+#[tokio::test]
+async fn test_async_try_update_rolls_back() {
+    let fail = Arc::new(AtomicBool::new(false));
+    let inner = Memory::new();
+    let adapter = FlakyAdapter {
+        inner: inner.clone(),
+        fail: Arc::clone(&fail),
+    };
+    let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+
+    // Closure error
+    let result = db
+        .try_update(|data| {
+            data.counter = 1;
+            Err::<(), _>(SaberError::Adapter("rejected".to_string()))
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(db.data().await.counter, 0);
+
+    // Write error
+    fail.store(true, Ordering::SeqCst);
+    let result = db
+        .try_update(|data| -> saberdb::Result<()> {
+            data.counter = 2;
+            Ok(())
+        })
+        .await;
+    assert!(matches!(result, Err(SaberError::Adapter(_))));
+    assert_eq!(db.data().await.counter, 0);
+
+    // Success returns the closure's value
+    fail.store(false, Ordering::SeqCst);
+    let doubled = db
+        .try_update(|data| -> saberdb::Result<u32> {
+            data.counter = 3;
+            Ok(data.counter * 2)
+        })
+        .await
+        .unwrap();
+    assert_eq!(doubled, 6);
+    assert_eq!(inner.read().await.unwrap().unwrap().counter, 3);
+}
+
+#[tokio::test]
+async fn test_async_update_async_awaits_under_lock() {
+    let db = Arc::new(SaberDB::new(Memory::new(), TestData::default()).await.unwrap());
+    let source = Arc::new(SaberDB::new(Memory::new(), TestData::default()).await.unwrap());
+    source.update(|data| data.counter = 40).await.unwrap();
+
+    // Each task reads, yields, then writes back; without the lock held across
+    // the await, increments would be lost
+    let tasks: Vec<_> = (0..8)
+        .map(|_| {
+            let db = Arc::clone(&db);
+            tokio::spawn(async move {
+                db.update_async(async |data| {
+                    let seen = data.counter;
+                    tokio::task::yield_now().await;
+                    data.counter = seen + 1;
+                })
+                .await
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+    assert_eq!(db.data().await.counter, 8);
+
+    let total = db
+        .update_async(async |data| {
+            let extra = source.data().await.counter;
+            data.counter += extra;
+            data.counter
+        })
+        .await
+        .unwrap();
+    assert_eq!(total, 48);
+}
+
+/// In-memory adapter whose writes wait for a permit, to hold I/O "in flight"
+struct GatedAdapter {
+    inner: Memory<TestData>,
+    started: Arc<Notify>,
+    permits: Arc<Semaphore>,
+    writes: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Adapter<TestData> for GatedAdapter {
+    async fn read(&self) -> saberdb::Result<Option<TestData>> {
+        self.inner.read().await
+    }
+
+    async fn write(&self, data: &TestData) -> saberdb::Result<()> {
+        self.started.notify_one();
+        self.permits.acquire().await.unwrap().forget();
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.inner.write(data).await
+    }
+}
+
+#[tokio::test]
+async fn test_async_lock_released_during_io() {
+    let inner = Memory::new();
+    let started = Arc::new(Notify::new());
+    let permits = Arc::new(Semaphore::new(0));
+    let adapter = GatedAdapter {
+        inner: inner.clone(),
+        started: Arc::clone(&started),
+        permits: Arc::clone(&permits),
+        writes: Arc::new(AtomicUsize::new(0)),
+    };
+    let db = Arc::new(SaberDB::new(adapter, TestData::default()).await.unwrap());
+
+    let first = tokio::spawn({
+        let db = Arc::clone(&db);
+        async move { db.update(|data| data.counter = 1).await }
+    });
+    started.notified().await;
+
+    // The first write is stuck in I/O, yet the data is free to read and mutate
+    tokio::time::timeout(Duration::from_secs(1), async {
+        assert_eq!(db.data().await.counter, 1);
+        db.data_mut().await.counter = 2;
+    })
+    .await
+    .expect("data lock held during I/O");
+
+    let second = tokio::spawn({
+        let db = Arc::clone(&db);
+        async move { db.write().await }
+    });
+
+    permits.add_permits(2);
+    first.await.unwrap().unwrap();
+    second.await.unwrap().unwrap();
+
+    // Storage ends at the latest mutation, never an older snapshot
+    assert_eq!(inner.read().await.unwrap().unwrap().counter, 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_async_group_commit_coalesces_writes() {
+    let inner = Memory::new();
+    let started = Arc::new(Notify::new());
+    let permits = Arc::new(Semaphore::new(0));
+    let writes = Arc::new(AtomicUsize::new(0));
+    let adapter = GatedAdapter {
+        inner: inner.clone(),
+        started: Arc::clone(&started),
+        permits: Arc::clone(&permits),
+        writes: Arc::clone(&writes),
+    };
+    let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+    let db = Arc::new(db.with_group_commit());
+
+    // One write in flight while 50 more updates pile up behind it
+    let spawn_update = |db: &Arc<SaberDB<TestData, GatedAdapter>>| {
+        let db = Arc::clone(db);
+        tokio::spawn(async move { db.update(|data| data.counter += 1).await })
+    };
+    let mut tasks = vec![spawn_update(&db)];
+    started.notified().await;
+    tasks.extend((0..50).map(|_| spawn_update(&db)));
+    while db.data().await.counter < 51 {
+        tokio::task::yield_now().await;
+    }
+
+    permits.add_permits(100);
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    // Every caller resolved after its change was stored, in two writes total
+    assert_eq!(inner.read().await.unwrap().unwrap().counter, 51);
+    assert_eq!(writes.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_async_group_commit_writes_fresh_default() {
+    let path = "test_async_group_default.json";
+    cleanup(path);
+
+    let db = SaberDB::new(JsonFile::new(path), TestData::default()).await.unwrap();
+    db.with_group_commit().write().await.unwrap();
+    assert!(std::path::Path::new(path).exists());
+
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_autosave_after_quiet_period() {
+    let adapter = Memory::new();
+    let db = SaberDB::new(adapter.clone(), TestData::default())
+        .await
+        .unwrap()
+        .with_autosave(Duration::from_millis(30), Duration::from_secs(10));
+
+    db.data_mut().await.counter = 5;
+    assert!(adapter.read().await.unwrap().is_none());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(adapter.read().await.unwrap().unwrap().counter, 5);
+}
+
+#[tokio::test]
+async fn test_async_autosave_max_interval_under_constant_changes() {
+    let adapter = Memory::new();
+    let db = SaberDB::new(adapter.clone(), TestData::default())
+        .await
+        .unwrap()
+        .with_autosave(Duration::from_secs(10), Duration::from_millis(50));
+
+    // Never quiet for long, yet saved once the max interval passes
+    for i in 1..=30 {
+        db.data_mut().await.counter = i;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let saved = adapter.read().await.unwrap().unwrap().counter;
+    assert!(saved > 0);
+}
+
+#[tokio::test]
+async fn test_async_flush_and_close() {
+    let adapter = Memory::new();
+    let db = SaberDB::new(adapter.clone(), TestData::default())
+        .await
+        .unwrap()
+        .with_autosave(Duration::from_secs(10), Duration::from_secs(10));
+
+    db.data_mut().await.counter = 1;
+    db.flush().await.unwrap();
+    assert_eq!(adapter.read().await.unwrap().unwrap().counter, 1);
+
+    db.data_mut().await.counter = 2;
+    db.close().await.unwrap();
+    assert_eq!(adapter.read().await.unwrap().unwrap().counter, 2);
+}
+
+#[tokio::test]
+async fn test_async_autosave_close_saves_pending_changes() {
+    let adapter = Memory::new();
+    let db = SaberDB::new(adapter.clone(), TestData::default())
+        .await
+        .unwrap()
+        .with_autosave(Duration::from_secs(10), Duration::from_secs(10));
+    db.data_mut().await.counter = 3;
+
+    // Saved by the time close returns, without waiting for the task
+    db.close().await.unwrap();
+    assert_eq!(adapter.read().await.unwrap().unwrap().counter, 3);
+}
+
+#[tokio::test]
+async fn test_async_dirty_tracking_skips_redundant_writes() {
+    let inner = Memory::new();
+    let writes = Arc::new(AtomicUsize::new(0));
+    let adapter = GatedAdapter {
+        inner: inner.clone(),
+        started: Arc::new(Notify::new()),
+        permits: Arc::new(Semaphore::new(Semaphore::MAX_PERMITS)),
+        writes: Arc::clone(&writes),
+    };
+    let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+    assert!(db.is_dirty());
+
+    db.write().await.unwrap();
+    db.write().await.unwrap();
+    db.flush().await.unwrap();
+    assert_eq!(writes.load(Ordering::SeqCst), 1);
+    assert!(!db.is_dirty());
+    assert!(db.last_written_at().is_some());
+
+    db.data_mut().await.counter = 4;
+    assert!(db.is_dirty());
+    db.write().await.unwrap();
+    assert_eq!(writes.load(Ordering::SeqCst), 2);
+
+    // Loaded data starts clean
+    let db2 = SaberDB::new(inner, TestData::default()).await.unwrap();
+    assert!(!db2.is_dirty());
+    assert!(db2.last_written_at().is_none());
+}
+
+#[tokio::test]
+async fn test_async_edit_commit_persists() {
+    let inner = Memory::new();
+    let db = SaberDB::new(inner.clone(), TestData::default()).await.unwrap();
+    db.write().await.unwrap();
+
+    let mut data = db.edit().await;
+    data.counter = 5;
+    data.message = "edited".to_string();
+    data.commit().await.unwrap();
+
+    assert!(!db.is_dirty());
+    let stored = inner.read().await.unwrap().unwrap();
+    assert_eq!(stored.counter, 5);
+    assert_eq!(stored.message, "edited");
+}
+
+#[tokio::test]
+async fn test_async_edit_discard_and_drop_restore() {
+    let inner = Memory::new();
+    let db = SaberDB::new(inner.clone(), TestData::default()).await.unwrap();
+    db.update(|data| data.counter = 1).await.unwrap();
+
+    let mut data = db.edit().await;
+    data.counter = 2;
+    data.discard();
+    assert_eq!(db.data().await.counter, 1);
+
+    {
+        let mut data = db.edit().await;
+        data.counter = 3;
+    }
+    assert_eq!(db.data().await.counter, 1);
+    assert!(!db.is_dirty());
+    assert_eq!(inner.read().await.unwrap().unwrap().counter, 1);
+}
+
+#[tokio::test]
+async fn test_async_edit_commit_reports_write_error() {
+    let fail = Arc::new(AtomicBool::new(true));
+    let adapter = FlakyAdapter {
+        inner: Memory::new(),
+        fail: Arc::clone(&fail),
+    };
+    let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+
+    let mut data = db.edit().await;
+    data.counter = 9;
+    assert!(data.commit().await.is_err());
+
+    // The edit stays in memory, waiting for the next write
+    assert_eq!(db.data().await.counter, 9);
+    assert!(db.is_dirty());
+    fail.store(false, Ordering::SeqCst);
+    db.flush().await.unwrap();
+    assert!(!db.is_dirty());
+}
+
+#[tokio::test]
+async fn test_async_watch_reloads_external_change() {
+    let path = "test_async_watch.json";
+    cleanup(path);
+
+    let db = SaberDB::new(JsonFile::new(path), TestData::default())
+        .await
+        .unwrap()
+        .with_watch(Duration::from_millis(10), ConflictPolicy::KeepMemory);
+    db.update(|data| data.counter = 1).await.unwrap();
+
+    fs::write(path, r#"{"counter": 10, "message": "by hand"}"#).unwrap();
+    let mut reloaded = false;
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        if db.data().await.counter == 10 {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded);
+    assert_eq!(db.data().await.message, "by hand");
+    assert!(!db.is_dirty());
+
+    db.close().await.unwrap();
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_reload_conflict_policy() {
+    let path = "test_async_reload_conflict.json";
+    cleanup(path);
+
+    let db = SaberDB::new(JsonFile::new(path), TestData::default()).await.unwrap();
+    db.write().await.unwrap();
+    assert!(!db.reload_if_changed(ConflictPolicy::Reload).await.unwrap());
+    db.data_mut().await.counter = 5;
+
+    fs::write(path, r#"{"counter": 20, "message": "external"}"#).unwrap();
+    assert!(!db.reload_if_changed(ConflictPolicy::KeepMemory).await.unwrap());
+    assert_eq!(db.data().await.counter, 5);
+    assert!(db.is_dirty());
+
+    assert!(db.reload_if_changed(ConflictPolicy::Reload).await.unwrap());
+    assert_eq!(db.data().await.counter, 20);
+    assert!(!db.is_dirty());
+
+    // Storage gone: data kept and written back on the next flush
+    fs::remove_file(path).unwrap();
+    db.reload().await.unwrap();
+    assert_eq!(db.data().await.counter, 20);
+    assert!(db.is_dirty());
+    db.flush().await.unwrap();
+    assert!(std::path::Path::new(path).exists());
+
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_version_check_detects_conflicting_writer() {
+    let path = "test_async_version_check.json";
+    cleanup(path);
+
+    let a = SaberDB::new(JsonFile::new(path).with_version_check(), TestData::default())
+        .await
+        .unwrap();
+    a.write().await.unwrap();
+    let b = SaberDB::new(JsonFile::new(path).with_version_check(), TestData::default())
+        .await
+        .unwrap();
+
+    b.update(|data| data.counter = 2).await.unwrap();
+    let result = a
+        .try_update(|data| -> saberdb::Result<()> {
+            data.counter = 1;
+            Ok(())
+        })
+        .await;
+    assert!(matches!(result, Err(SaberError::Conflict(_))));
+    // Rolled back, so a reload and retry starts from the other writer's data
+    assert_eq!(a.data().await.counter, 0);
+
+    a.reload().await.unwrap();
+    a.update(|data| data.counter += 1).await.unwrap();
+    assert_eq!(a.data().await.counter, 3);
+
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_subscribe_receives_revisions() {
+    let db = SaberDB::new(Memory::new(), TestData::default()).await.unwrap();
+    let mut events = db.subscribe();
+
+    db.update(|data| data.counter = 1).await.unwrap();
+    db.data_mut().await.counter = 2;
+    db.write().await.unwrap();
+    // Nothing changed, nothing written, no event
+    db.write().await.unwrap();
+
+    let first = events.recv().await.unwrap();
+    let second = events.recv().await.unwrap();
+    assert!(first.revision < second.revision);
+    assert!(first.diff.is_none());
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_async_subscribe_with_diffs() {
+    let db = SaberDB::new(Memory::new(), TestData::default())
+        .await
+        .unwrap()
+        .with_diffs();
+    let mut events = db.subscribe();
+
+    db.update(|data| data.counter = 7).await.unwrap();
+    let event = events.recv().await.unwrap();
+    assert_eq!(
+        event.diff.as_deref(),
+        Some(
+            &[PatchOp::Replace {
+                path: "/counter".to_string(),
+                value: serde_json::json!(7),
+            }][..]
+        )
+    );
+
+    db.update(|data| data.message = "hi".to_string()).await.unwrap();
+    let event = events.recv().await.unwrap();
+    assert_eq!(
+        event.diff.as_deref(),
+        Some(
+            &[PatchOp::Replace {
+                path: "/message".to_string(),
+                value: serde_json::json!("hi"),
+            }][..]
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_async_no_event_for_failed_write() {
+    let fail = Arc::new(AtomicBool::new(true));
+    let adapter = FlakyAdapter {
+        inner: Memory::new(),
+        fail: Arc::clone(&fail),
+    };
+    let db = SaberDB::new(adapter, TestData::default()).await.unwrap();
+    let mut events = db.subscribe();
+
+    assert!(db.update(|data| data.counter = 1).await.is_err());
+    assert!(events.try_recv().is_err());
+
+    fail.store(false, Ordering::SeqCst);
+    db.flush().await.unwrap();
+    assert!(events.recv().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_async_watch_path_only_fires_on_change() {
+    let db = SaberDB::new(Memory::new(), TestData::default()).await.unwrap();
+    db.write().await.unwrap();
+    let mut counter = db.watch_path("/counter").await.unwrap();
+    let mut missing = db.watch_path("/missing").await.unwrap();
+
+    db.update(|data| data.message = "unrelated".to_string()).await.unwrap();
+    db.update(|data| data.counter = 1).await.unwrap();
+    db.update(|data| data.counter = 1).await.unwrap();
+    db.update(|data| data.counter = 2).await.unwrap();
+
+    let event = counter.recv().await.unwrap();
+    assert_eq!(event.pointer, "/counter");
+    assert_eq!(event.old, Some(serde_json::json!(0)));
+    assert_eq!(event.new, Some(serde_json::json!(1)));
+    let event = counter.recv().await.unwrap();
+    assert_eq!(event.old, Some(serde_json::json!(1)));
+    assert_eq!(event.new, Some(serde_json::json!(2)));
+    assert!(counter.try_recv().is_err());
+    assert!(missing.try_recv().is_err());
+
+    // Only written changes count
+    db.data_mut().await.counter = 3;
+    assert!(counter.try_recv().is_err());
+    db.write().await.unwrap();
+    assert_eq!(counter.recv().await.unwrap().new, Some(serde_json::json!(3)));
+}
+
+#[tokio::test]
+async fn test_async_watch_path_compares_against_storage() {
+    let db = SaberDB::new(Memory::new(), TestData::default()).await.unwrap();
+
+    // Nothing is stored yet, so the first write creates the path
+    let mut counter = db.watch_path("/counter").await.unwrap();
+    db.write().await.unwrap();
+    let event = counter.try_recv().unwrap();
+    assert_eq!((event.old, event.new), (None, Some(serde_json::json!(0))));
+
+    // Unsaved changes are not the baseline, whether joining a watched pointer
+    // or starting a new one
+    {
+        let mut data = db.data_mut().await;
+        data.counter = 5;
+        data.message = "unsaved".to_string();
+    }
+    let mut late = db.watch_path("/counter").await.unwrap();
+    let mut message = db.watch_path("/message").await.unwrap();
+    // Events are sent before the write returns
+    db.write().await.unwrap();
+
+    for watcher in [&mut counter, &mut late] {
+        let event = watcher.try_recv().unwrap();
+        assert_eq!(event.old, Some(serde_json::json!(0)));
+        assert_eq!(event.new, Some(serde_json::json!(5)));
+    }
+    let event = message.try_recv().unwrap();
+    assert_eq!(event.old, Some(serde_json::json!("default")));
+    assert_eq!(event.new, Some(serde_json::json!("unsaved")));
+}
+
+#[tokio::test]
+async fn test_async_watch_path_rejects_invalid_pointer() {
+    let db = SaberDB::new(Memory::new(), TestData::default()).await.unwrap();
+    assert!(matches!(
+        db.watch_path("counter").await,
+        Err(SaberError::InvalidPointer(pointer)) if pointer == "counter"
+    ));
+    assert!(matches!(
+        db.watch_path("/a~2b").await,
+        Err(SaberError::InvalidPointer(_))
+    ));
+    assert!(db.watch_path("/a~1b~0").await.is_ok());
+    assert!(db.watch_path("").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_async_undo_redo_history() {
+    let inner = Memory::new();
+    let db = SaberDB::new(inner.clone(), TestData::default())
+        .await
+        .unwrap()
+        .with_history(10);
+
+    db.update(|data| data.counter = 1).await.unwrap();
+    let mut edit = db.edit().await;
+    edit.message = "edited".to_string();
+    edit.commit().await.unwrap();
+    // Discarded edits and failed updates leave no history
+    db.edit().await.discard();
+    let _ = db
+        .try_update(|_| Err::<(), _>(SaberError::Adapter("nope".to_string())))
+        .await;
+    assert_eq!(db.history_len(), 2);
+
+    assert!(db.undo().await.unwrap());
+    assert_eq!(db.data().await.message, "default");
+    assert_eq!(inner.read().await.unwrap().unwrap().message, "default");
+    assert!(db.undo().await.unwrap());
+    assert_eq!(db.data().await.counter, 0);
+    assert!(!db.undo().await.unwrap());
+
+    assert!(db.redo().await.unwrap());
+    assert!(db.redo().await.unwrap());
+    assert!(!db.redo().await.unwrap());
+    assert_eq!(*db.data().await, TestData {
+        counter: 1,
+        message: "edited".to_string(),
+    });
+}
+
+#[tokio::test]
+async fn test_async_snapshots_create_list_restore() {
+    let path = "test_async_snapshots.json";
+    cleanup(path);
+
+    let db = SaberDB::new(JsonFile::new(path), TestData::default()).await.unwrap();
+    db.update(|data| data.counter = 1).await.unwrap();
+    db.snapshot("checkpoint").await.unwrap();
+    assert!(matches!(
+        db.snapshot("checkpoint").await,
+        Err(SaberError::SnapshotExists(_))
+    ));
+
+    db.update(|data| data.counter = 2).await.unwrap();
+    db.restore("checkpoint").await.unwrap();
+    assert_eq!(db.data().await.counter, 1);
+    assert!(!db.is_dirty());
+    assert_eq!(db.list_snapshots().await.unwrap(), vec!["checkpoint"]);
+
+    let memory = SaberDB::new(Memory::new(), TestData::default()).await.unwrap();
+    memory.snapshot("a").await.unwrap();
+    assert!(matches!(
+        memory.restore("b").await,
+        Err(SaberError::SnapshotNotFound(_))
+    ));
+
+    cleanup(path);
+}
+
+#[cfg(feature = "yaml")]
+#[tokio::test]
+async fn test_async_yaml_file_roundtrip() {
+    use saberdb::YamlFile;
+
+    let path = "test_async_yaml.yaml";
+    cleanup(path);
+
+    let db = SaberDB::new(YamlFile::new(path), TestData::default()).await.unwrap();
+    db.update(|data| data.message = "yaml".to_string()).await.unwrap();
+    db.snapshot("first").await.unwrap();
+    assert!(std::path::Path::new("test_async_yaml.snapshots/first.yaml").exists());
+
+    let reopened = SaberDB::new(YamlFile::new(path), TestData::default()).await.unwrap();
+    assert_eq!(reopened.data().await.message, "yaml");
+
+    cleanup(path);
+}
+
+#[cfg(feature = "toml")]
+#[tokio::test]
+async fn test_async_toml_file_roundtrip() {
+    use saberdb::TomlFile;
+
+    let path = "test_async_toml.toml";
+    cleanup(path);
+
+    let db = SaberDB::new(TomlFile::new(path), TestData::default()).await.unwrap();
+    db.update(|data| data.counter = 8).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        "counter = 8\nmessage = \"default\"\n"
+    );
+
+    let reopened = SaberDB::new(TomlFile::new(path), TestData::default()).await.unwrap();
+    assert_eq!(reopened.data().await.counter, 8);
+
+    cleanup(path);
+}
+
+#[cfg(feature = "msgpack")]
+#[tokio::test]
+async fn test_async_msgpack_file_roundtrip() {
+    use saberdb::MessagePackFile;
+
+    let path = "test_async_msgpack.msgpack";
+    cleanup(path);
+
+    let db = SaberDB::new(MessagePackFile::new(path), TestData::default()).await.unwrap();
+    db.update(|data| data.counter = 9).await.unwrap();
+
+    let reopened = SaberDB::new(MessagePackFile::new(path), TestData::default()).await.unwrap();
+    assert_eq!(reopened.data().await.counter, 9);
+
+    cleanup(path);
+}
+
+#[cfg(feature = "cbor")]
+#[tokio::test]
+async fn test_async_cbor_file_roundtrip() {
+    use saberdb::CborFile;
+
+    let path = "test_async_cbor.cbor";
+    cleanup(path);
+
+    let db = SaberDB::new(CborFile::new(path), TestData::default()).await.unwrap();
+    db.update(|data| data.counter = 10).await.unwrap();
+
+    let reopened = SaberDB::new(CborFile::new(path), TestData::default()).await.unwrap();
+    assert_eq!(reopened.data().await.counter, 10);
+
+    cleanup(path);
+}
+
+#[cfg(feature = "bincode")]
+#[tokio::test]
+async fn test_async_bincode_file_roundtrip() {
+    use saberdb::BincodeFile;
+
+    let path = "test_async_bincode.bin";
+    cleanup(path);
+
+    let db = SaberDB::new(BincodeFile::new(path), TestData::default()).await.unwrap();
+    db.update(|data| data.counter = 11).await.unwrap();
+
+    let reopened = SaberDB::new(BincodeFile::new(path), TestData::default()).await.unwrap();
+    assert_eq!(reopened.data().await.counter, 11);
+
+    cleanup(path);
+}
+
+#[tokio::test]
+async fn test_async_jsonc_file_tolerates_trailing_commas() {
+    use saberdb::JsoncFile;
+
+    let path = "test_async_jsonc.json";
+    cleanup(path);
+    fs::write(path, "{\"counter\": 1, \"message\": \"hi\",}").unwrap();
+    let db = SaberDB::new(JsoncFile::new(path), TestData::default()).await.unwrap();
+    db.update(|data| data.counter = 2).await.unwrap();
+
+    // Without comments the output is plain JSON
+    let expected = serde_json::to_string_pretty(&*db.data().await).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), expected);
+
+    cleanup(path);
+}
@@ -1,6 +1,12 @@
-use saberdb::{JsonFileSync, SaberDBSync};
-use serde::{Deserialize, Serialize};
+use saberdb::{
+    AdapterSync, ConflictPolicy, Durability, FileSync, Format, JsonFileSync, LockMode,
+    MemorySync, OnDrop, Recovery, RecoveryAction, SaberDBSync, SaberError, WalFileSync,
+};
+use std::time::Duration;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 struct TestData {
@@ -20,6 +26,19 @@ impl Default for TestData {
 fn cleanup(path: &str) {
     let _ = fs::remove_file(path);
     let _ = fs::remove_file(format!("{}.tmp", path));
+    let _ = fs::remove_file(format!("{}.lock", path));
+    let _ = fs::remove_file(format!("{}.wal", path));
+    let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+    let _ = fs::remove_dir_all(format!("{}.snapshots", stem));
+    for k in 1..=5 {
+        let _ = fs::remove_file(format!("{}.{}", path, k));
+    }
+    let corrupt = format!("{}.corrupt-", path);
+    for entry in fs::read_dir(".").unwrap().filter_map(|e| e.ok()) {
+        if entry.file_name().to_string_lossy().starts_with(&corrupt) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
 }
 
 #[test]
@@ -191,3 +210,1079 @@ fn test_json_is_pretty_formatted() {
 
     cleanup(path);
 }
+
+#[test]
+fn test_durability_levels_round_trip() {
+    for (path, durability) in [
+        ("test_durability_none.json", Durability::None),
+        ("test_durability_data.json", Durability::Data),
+        ("test_durability_full.json", Durability::Full),
+    ] {
+        cleanup(path);
+
+        let adapter = JsonFileSync::new(path).with_durability(durability);
+        let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+        db.update(|data| data.counter = 7).unwrap();
+
+        let adapter = JsonFileSync::new(path);
+        let db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+        assert_eq!(db.data().counter, 7);
+
+        cleanup(path);
+    }
+}
+
+fn temp_files(path: &str) -> Vec<String> {
+    let prefix = format!(".{}.", path);
+    fs::read_dir(".")
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|n| n.starts_with(&prefix) && n.ends_with(".tmp"))
+        .collect()
+}
+
+#[test]
+fn test_write_leaves_sibling_tmp_file_alone() {
+    let path = "test_sibling.json";
+    let sibling = "test_sibling.tmp";
+    cleanup(path);
+    fs::write(sibling, "not ours").unwrap();
+
+    let adapter = JsonFileSync::new(path);
+    let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    db.update(|data| data.counter = 1).unwrap();
+
+    assert_eq!(fs::read_to_string(sibling).unwrap(), "not ours");
+    assert!(temp_files(path).is_empty());
+
+    let _ = fs::remove_file(sibling);
+    cleanup(path);
+}
+
+#[test]
+fn test_stale_temp_files_removed_on_new() {
+    let path = "test_stale_temp.json";
+    cleanup(path);
+
+    // A temp file from a process that no longer exists
+    let stale = format!(".{}.4294967295.0.tmp", path);
+    let file = fs::File::create(&stale).unwrap();
+    let two_hours_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(2 * 60 * 60);
+    file.set_modified(two_hours_ago).unwrap();
+    drop(file);
+
+    // A temp file belonging to this process may be an in-flight write
+    let ours = format!(".{}.{}.0.tmp", path, std::process::id());
+    fs::write(&ours, "in flight").unwrap();
+
+    // So may a recent one whose owner we cannot see, e.g. in another PID namespace
+    let recent = format!(".{}.4294967295.1.tmp", path);
+    fs::write(&recent, "in flight").unwrap();
+
+    let _adapter = JsonFileSync::new(path);
+
+    assert!(!std::path::Path::new(&stale).exists());
+    assert!(std::path::Path::new(&ours).exists());
+    assert!(std::path::Path::new(&recent).exists());
+
+    let _ = fs::remove_file(ours);
+    let _ = fs::remove_file(recent);
+    cleanup(path);
+}
+
+#[test]
+fn test_exclusive_lock_rejects_second_owner() {
+    let path = "test_lock_exclusive.json";
+    cleanup(path);
+
+    let adapter = JsonFileSync::new(path).with_lock(LockMode::Exclusive);
+    let owner = SaberDBSync::new(adapter, TestData::default()).unwrap();
+
+    let adapter = JsonFileSync::new(path).with_lock(LockMode::Exclusive);
+    let result = SaberDBSync::new(adapter, TestData::default());
+    assert!(matches!(result, Err(SaberError::Locked(_))));
+
+    // Released once the owning database is dropped
+    drop(owner);
+    let adapter = JsonFileSync::new(path).with_lock(LockMode::Exclusive);
+    assert!(SaberDBSync::new(adapter, TestData::default()).is_ok());
+
+    cleanup(path);
+}
+
+#[test]
+fn test_per_operation_lock_times_out_while_owned() {
+    let path = "test_lock_per_op.json";
+    cleanup(path);
+
+    let adapter = JsonFileSync::new(path).with_lock(LockMode::Exclusive);
+    let mut owner = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    owner.update(|data| data.counter = 1).unwrap();
+
+    let mode = LockMode::PerOperation {
+        timeout: Duration::from_millis(50),
+    };
+    let result = SaberDBSync::new(JsonFileSync::new(path).with_lock(mode), TestData::default());
+    assert!(matches!(result, Err(SaberError::Locked(_))));
+
+    drop(owner);
+
+    // Per-operation locks are released between calls, so two instances can share the file
+    let mut a = SaberDBSync::new(JsonFileSync::new(path).with_lock(mode), TestData::default()).unwrap();
+    let b = SaberDBSync::new(JsonFileSync::new(path).with_lock(mode), TestData::default()).unwrap();
+    a.update(|data| data.counter = 2).unwrap();
+    b.write().unwrap();
+    assert_eq!(b.data().counter, 1);
+
+    cleanup(path);
+}
+
+#[test]
+fn test_backups_rotate_and_restore() {
+    let path = "test_backups.json";
+    cleanup(path);
+
+    let adapter = JsonFileSync::new(path).with_backups(2);
+    let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    for i in 1..=4 {
+        db.update(|data| data.counter = i).unwrap();
+    }
+
+    let backup = |k: usize| -> TestData {
+        serde_json::from_slice(&fs::read(format!("{}.{}", path, k)).unwrap()).unwrap()
+    };
+    assert_eq!(backup(1).counter, 3);
+    assert_eq!(backup(2).counter, 2);
+    assert!(!std::path::Path::new(&format!("{}.3", path)).exists());
+
+    // Roll back the last update; the replaced state becomes backup 1
+    db.restore_backup(1).unwrap();
+    assert_eq!(db.data().counter, 3);
+    assert_eq!(backup(1).counter, 4);
+
+    let db2 = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(db2.data().counter, 3);
+
+    assert!(matches!(db.restore_backup(3), Err(SaberError::BackupNotFound(3))));
+
+    cleanup(path);
+}
+
+#[test]
+fn test_restore_backup_unsupported_by_memory() {
+    let mut db = SaberDBSync::new(MemorySync::new(), TestData::default()).unwrap();
+    assert!(matches!(db.restore_backup(1), Err(SaberError::Unsupported(_))));
+}
+
+#[test]
+fn test_corrupt_file_fails_by_default() {
+    let path = "test_corrupt_fail.json";
+    cleanup(path);
+    fs::write(path, "{\"counter\": 4").unwrap();
+
+    let result = SaberDBSync::new(JsonFileSync::new(path), TestData::default());
+    let Err(error) = result else {
+        panic!("corrupt file was read");
+    };
+    assert!(matches!(error, SaberError::Serialization(_)));
+    // The decoder's error stays reachable for error-chain reporting
+    let source = std::error::Error::source(&error).unwrap();
+    assert!(source.downcast_ref::<serde_json::Error>().is_some());
+
+    cleanup(path);
+}
+
+#[test]
+fn test_corrupt_file_quarantined() {
+    let path = "test_corrupt_quarantine.json";
+    cleanup(path);
+    fs::write(path, "{\"counter\": 4").unwrap();
+
+    let adapter = JsonFileSync::new(path).with_recovery(Recovery::Quarantine);
+    let db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    assert_eq!(db.data(), &TestData::default());
+
+    let report = db.adapter().last_recovery().unwrap();
+    let RecoveryAction::Quarantined { path: moved } = report.action else {
+        panic!("unexpected action: {:?}", report.action);
+    };
+    assert_eq!(fs::read_to_string(moved).unwrap(), "{\"counter\": 4");
+    assert!(!std::path::Path::new(path).exists());
+
+    cleanup(path);
+}
+
+#[test]
+fn test_corrupt_file_restored_from_backup() {
+    let path = "test_corrupt_backup.json";
+    cleanup(path);
+
+    {
+        let adapter = JsonFileSync::new(path).with_backups(2);
+        let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+        db.update(|data| data.counter = 1).unwrap();
+        db.update(|data| data.counter = 2).unwrap();
+        db.update(|data| data.counter = 3).unwrap();
+    }
+    // Damage the current file and the newest backup
+    fs::write(path, "garbage").unwrap();
+    fs::write(format!("{}.1", path), "").unwrap();
+
+    let adapter = JsonFileSync::new(path)
+        .with_backups(2)
+        .with_recovery(Recovery::RestoreBackup);
+    let db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    assert_eq!(db.data().counter, 1);
+
+    let report = db.adapter().last_recovery().unwrap();
+    assert!(matches!(report.action, RecoveryAction::RestoredBackup { backup: 2, .. }));
+    assert!(!report.error.is_empty());
+
+    // The backup is back in place, so a plain reopen sees it too
+    assert!(std::path::Path::new(path).exists());
+    let reopened = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(reopened.data().counter, 1);
+
+    cleanup(path);
+}
+
+#[test]
+fn test_corrupt_file_use_default_keeps_file() {
+    let path = "test_corrupt_default.json";
+    cleanup(path);
+    fs::write(path, "[]").unwrap();
+
+    let adapter = JsonFileSync::new(path).with_recovery(Recovery::UseDefault);
+    let db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    assert_eq!(db.data().counter, 0);
+    assert_eq!(db.adapter().last_recovery().unwrap().action, RecoveryAction::UsedDefault);
+    assert_eq!(fs::read_to_string(path).unwrap(), "[]");
+
+    cleanup(path);
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+struct Journal {
+    title: String,
+    entries: Vec<String>,
+    tags: std::collections::BTreeMap<String, u32>,
+}
+
+#[test]
+fn test_wal_appends_changes_and_replays() {
+    let path = "test_wal.json";
+    cleanup(path);
+
+    {
+        let mut db = SaberDBSync::new(WalFileSync::new(path), Journal::default()).unwrap();
+        db.update(|j| j.title = "diary".to_string()).unwrap();
+        let snapshot = fs::read_to_string(path).unwrap();
+
+        db.update(|j| j.entries.push("one".to_string())).unwrap();
+        db.update(|j| j.entries.push("two/~".to_string())).unwrap();
+        db.update(|j| {
+            j.entries.remove(0);
+            j.tags.insert("mood".to_string(), 3);
+        })
+        .unwrap();
+
+        // Later writes only touch the log
+        assert_eq!(fs::read_to_string(path).unwrap(), snapshot);
+        let log = fs::read_to_string(format!("{}.wal", path)).unwrap();
+        assert_eq!(log.lines().count(), 4); // header + 3 records
+    }
+
+    let db = SaberDBSync::new(WalFileSync::new(path), Journal::default()).unwrap();
+    assert_eq!(db.data().title, "diary");
+    assert_eq!(db.data().entries, vec!["two/~".to_string()]);
+    assert_eq!(db.data().tags.get("mood"), Some(&3));
+
+    cleanup(path);
+}
+
+#[test]
+fn test_wal_compacts_into_snapshot() {
+    let path = "test_wal_compact.json";
+    cleanup(path);
+
+    let adapter = WalFileSync::new(path).with_compaction(3);
+    let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    for i in 1..=5 {
+        db.update(|data| data.counter = i).unwrap();
+    }
+
+    // Write 1 snapshots, 2-4 append, 5 compacts
+    let snapshot: TestData = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+    assert_eq!(snapshot.counter, 5);
+    let log = fs::read_to_string(format!("{}.wal", path)).unwrap();
+    assert_eq!(log.lines().count(), 1);
+
+    db.update(|data| data.counter = 6).unwrap();
+    db.adapter().compact().unwrap();
+    let snapshot: TestData = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+    assert_eq!(snapshot.counter, 6);
+
+    cleanup(path);
+}
+
+#[test]
+fn test_wal_ignores_torn_record_and_stale_log() {
+    let path = "test_wal_torn.json";
+    cleanup(path);
+
+    {
+        let mut db = SaberDBSync::new(WalFileSync::new(path), TestData::default()).unwrap();
+        db.update(|data| data.counter = 1).unwrap();
+        db.update(|data| data.counter = 2).unwrap();
+    }
+
+    // A crash mid-append leaves a partial last line
+    let log_path = format!("{}.wal", path);
+    let mut log = fs::read_to_string(&log_path).unwrap();
+    log.push_str("[{\"op\":\"replace\",\"pa");
+    fs::write(&log_path, log).unwrap();
+
+    {
+        let mut db = SaberDBSync::new(WalFileSync::new(path), TestData::default()).unwrap();
+        assert_eq!(db.data().counter, 2);
+        db.update(|data| data.counter = 3).unwrap();
+    }
+    let db = SaberDBSync::new(WalFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(db.data().counter, 3);
+
+    // A snapshot replaced without its log (interrupted compaction) ignores the old log
+    fs::write(path, r#"{"counter": 10, "message": "compacted"}"#).unwrap();
+    let mut db = SaberDBSync::new(WalFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(db.data().counter, 10);
+
+    // Writes after that are not lost to the stale log
+    db.update(|data| data.counter = 11).unwrap();
+    drop(db);
+    let db = SaberDBSync::new(WalFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(db.data().counter, 11);
+
+    cleanup(path);
+}
+
+#[test]
+fn test_wal_write_after_failed_append() {
+    let path = "test_wal_failed_append.json";
+    let log_path = format!("{}.wal", path);
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(WalFileSync::new(path), TestData::default()).unwrap();
+    db.update(|data| data.counter = 1).unwrap();
+
+    // Make the next append fail, then leave the log as if part of it landed
+    let log = fs::read_to_string(&log_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+    fs::create_dir(&log_path).unwrap();
+    assert!(db.update(|data| data.counter = 2).is_err());
+    fs::remove_dir(&log_path).unwrap();
+    fs::write(&log_path, log + "[{\"op\":\"repl").unwrap();
+
+    db.update(|data| data.counter = 3).unwrap();
+    drop(db);
+    let db = SaberDBSync::new(WalFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(db.data().counter, 3);
+
+    cleanup(path);
+}
+
+/// In-memory adapter whose writes fail while `fail` is set
+struct FlakyAdapter {
+    inner: MemorySync<TestData>,
+    fail: Arc<AtomicBool>,
+}
+
+impl AdapterSync<TestData> for FlakyAdapter {
+    fn read(&self) -> saberdb::Result<Option<TestData>> {
+        self.inner.read()
+    }
+
+    fn write(&self, data: &TestData) -> saberdb::Result<()> {
+        if self.fail.load(Ordering::SeqCst) {
+            return Err(SaberError::Adapter("disk full".to_string()));
+        }
+        self.inner.write(data)
+    }
+}
+
+#[test]
+fn test_try_update_returns_value_and_persists() {
+    let adapter = MemorySync::new();
+    let mut db = SaberDBSync::new(adapter.clone(), TestData::default()).unwrap();
+
+    let previous = db
+        .try_update(|data| -> saberdb::Result<u32> {
+            let previous = data.counter;
+            data.counter = 5;
+            Ok(previous)
+        })
+        .unwrap();
+
+    assert_eq!(previous, 0);
+    assert_eq!(adapter.read().unwrap().unwrap().counter, 5);
+}
+
+#[test]
+fn test_try_update_rolls_back_on_closure_error() {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    enum AppError {
+        Invalid,
+        Db(SaberError),
+    }
+    impl From<SaberError> for AppError {
+        fn from(e: SaberError) -> Self {
+            AppError::Db(e)
+        }
+    }
+
+    let mut db = SaberDBSync::new(MemorySync::new(), TestData::default()).unwrap();
+    let result = db.try_update(|data| {
+        data.counter = 99;
+        data.message = "half done".to_string();
+        Err::<(), _>(AppError::Invalid)
+    });
+
+    assert!(matches!(result, Err(AppError::Invalid)));
+    assert_eq!(db.data(), &TestData::default());
+}
+
+#[test]
+fn test_try_update_rolls_back_on_write_error() {
+    let fail = Arc::new(AtomicBool::new(true));
+    let adapter = FlakyAdapter {
+        inner: MemorySync::new(),
+        fail: Arc::clone(&fail),
+    };
+    let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+
+    let result = db.try_update(|data| -> saberdb::Result<()> {
+        data.counter = 1;
+        Ok(())
+    });
+    assert!(matches!(result, Err(SaberError::Adapter(_))));
+    assert_eq!(db.data().counter, 0);
+
+    fail.store(false, Ordering::SeqCst);
+    db.try_update(|data| -> saberdb::Result<()> {
+        data.counter = 2;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(db.data().counter, 2);
+}
+
+#[test]
+fn test_dirty_tracking_skips_redundant_writes() {
+    let path = "test_dirty.json";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    // Never stored yet
+    assert!(db.is_dirty());
+    assert!(db.last_written_at().is_none());
+
+    db.write().unwrap();
+    assert!(!db.is_dirty());
+    let written_at = db.last_written_at().unwrap();
+
+    // Nothing changed: the write is skipped, so the deleted file stays gone
+    fs::remove_file(path).unwrap();
+    db.write().unwrap();
+    assert!(!std::path::Path::new(path).exists());
+    assert_eq!(db.last_written_at(), Some(written_at));
+
+    db.data_mut().counter = 1;
+    assert!(db.is_dirty());
+    db.write().unwrap();
+    assert!(!db.is_dirty());
+    assert!(std::path::Path::new(path).exists());
+
+    // Freshly loaded data is clean
+    let db2 = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert!(!db2.is_dirty());
+
+    cleanup(path);
+}
+
+#[test]
+fn test_failed_try_update_restores_dirty_flag() {
+    let fail = Arc::new(AtomicBool::new(false));
+    let adapter = FlakyAdapter {
+        inner: MemorySync::new(),
+        fail: Arc::clone(&fail),
+    };
+    let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    db.write().unwrap();
+
+    fail.store(true, Ordering::SeqCst);
+    let result = db.try_update(|data| -> saberdb::Result<()> {
+        data.counter = 1;
+        Ok(())
+    });
+    assert!(result.is_err());
+    assert!(!db.is_dirty());
+}
+
+#[test]
+fn test_on_drop_flush_writes_unsaved_changes() {
+    let path = "test_on_drop_flush.json";
+    cleanup(path);
+
+    {
+        let mut db = SaberDBSync::new(JsonFileSync::new(path), TestData::default())
+            .unwrap()
+            .with_on_drop(OnDrop::Flush);
+        db.data_mut().counter = 7;
+    }
+
+    let db = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(db.data().counter, 7);
+
+    cleanup(path);
+}
+
+#[test]
+fn test_on_drop_ignore_discards_unsaved_changes() {
+    let path = "test_on_drop_ignore.json";
+    cleanup(path);
+
+    {
+        let mut db = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+        db.data_mut().counter = 7;
+    }
+    assert!(!std::path::Path::new(path).exists());
+
+    cleanup(path);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn test_on_drop_panic_catches_unsaved_changes() {
+    let result = std::panic::catch_unwind(|| {
+        let mut db = SaberDBSync::new(MemorySync::new(), TestData::default())
+            .unwrap()
+            .with_on_drop(OnDrop::Panic);
+        db.data_mut().counter = 1;
+    });
+    assert!(result.is_err());
+
+    // Saved data drops quietly
+    let db = SaberDBSync::new(MemorySync::new(), TestData::default())
+        .unwrap()
+        .with_on_drop(OnDrop::Panic);
+    db.write().unwrap();
+}
+
+#[test]
+fn test_on_drop_ignores_untouched_default() {
+    let path = "test_on_drop_untouched.json";
+    cleanup(path);
+
+    for policy in [OnDrop::Ignore, OnDrop::Flush, OnDrop::Warn, OnDrop::Panic] {
+        let db = SaberDBSync::new(JsonFileSync::new(path), TestData::default())
+            .unwrap()
+            .with_on_drop(policy);
+        // Not yet stored, but not a change the caller made either
+        assert!(db.is_dirty());
+        drop(db);
+        assert!(!std::path::Path::new(path).exists(), "{:?} wrote the default", policy);
+    }
+
+    cleanup(path);
+}
+
+#[test]
+fn test_data_mut_guard_writes_on_drop() {
+    let path = "test_data_mut_guard.json";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    {
+        let mut data = db.data_mut_guard();
+        data.counter = 3;
+        data.message = "guarded".to_string();
+    }
+    assert!(!db.is_dirty());
+
+    let mut data = db.data_mut_guard();
+    data.counter += 1;
+    data.commit().unwrap();
+
+    let db2 = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(db2.data().counter, 4);
+    assert_eq!(db2.data().message, "guarded");
+
+    cleanup(path);
+}
+
+#[test]
+fn test_data_mut_guard_commit_reports_write_error() {
+    let fail = Arc::new(AtomicBool::new(true));
+    let adapter = FlakyAdapter {
+        inner: MemorySync::new(),
+        fail: Arc::clone(&fail),
+    };
+    let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+
+    let mut data = db.data_mut_guard();
+    data.counter = 1;
+    assert!(data.commit().is_err());
+    assert!(db.is_dirty());
+}
+
+#[test]
+fn test_reload_picks_up_external_edit() {
+    let path = "test_reload.json";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    db.update(|data| data.counter = 1).unwrap();
+    // Our own write is not an external change
+    assert!(!db.reload_if_changed(ConflictPolicy::Reload).unwrap());
+
+    fs::write(path, r#"{"counter": 10, "message": "by hand"}"#).unwrap();
+    assert!(db.reload_if_changed(ConflictPolicy::KeepMemory).unwrap());
+    assert_eq!(db.data().counter, 10);
+    assert_eq!(db.data().message, "by hand");
+    assert!(!db.is_dirty());
+    assert!(!db.reload_if_changed(ConflictPolicy::Reload).unwrap());
+
+    db.data_mut().counter = 11;
+    db.reload().unwrap();
+    assert_eq!(db.data().counter, 10);
+
+    cleanup(path);
+}
+
+#[test]
+fn test_reload_conflict_policy_with_pending_changes() {
+    let path = "test_reload_conflict.json";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    db.write().unwrap();
+    db.data_mut().counter = 5;
+
+    fs::write(path, r#"{"counter": 20, "message": "external"}"#).unwrap();
+    assert!(!db.reload_if_changed(ConflictPolicy::KeepMemory).unwrap());
+    assert_eq!(db.data().counter, 5);
+    assert!(db.is_dirty());
+
+    assert!(db.reload_if_changed(ConflictPolicy::Reload).unwrap());
+    assert_eq!(db.data().counter, 20);
+    assert!(!db.is_dirty());
+
+    cleanup(path);
+}
+
+#[test]
+fn test_version_check_detects_conflicting_writer() {
+    let path = "test_version_check.json";
+    cleanup(path);
+
+    let mut a = SaberDBSync::new(
+        JsonFileSync::new(path).with_version_check(),
+        TestData::default(),
+    )
+    .unwrap();
+    a.write().unwrap();
+    let version = a.adapter().version().unwrap();
+
+    let mut b = SaberDBSync::new(
+        JsonFileSync::new(path).with_version_check(),
+        TestData::default(),
+    )
+    .unwrap();
+    assert_eq!(b.adapter().version(), Some(version.clone()));
+
+    b.update(|data| data.counter = 2).unwrap();
+    assert_ne!(b.adapter().version(), Some(version));
+
+    // `a` last saw the file before `b` changed it
+    let result = a.update(|data| data.counter = 1);
+    assert!(matches!(result, Err(SaberError::Conflict(_))));
+    assert!(a.is_dirty());
+    let stored = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(stored.data().counter, 2);
+
+    // After catching up, the write goes through
+    a.reload().unwrap();
+    a.update(|data| data.counter += 1).unwrap();
+    assert_eq!(a.data().counter, 3);
+
+    cleanup(path);
+}
+
+#[test]
+fn test_version_check_ignores_touch_without_change() {
+    let path = "test_version_touch.json";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(
+        JsonFileSync::new(path).with_version_check(),
+        TestData::default(),
+    )
+    .unwrap();
+    db.write().unwrap();
+
+    // Rewritten with identical contents
+    let bytes = fs::read(path).unwrap();
+    fs::remove_file(path).unwrap();
+    fs::write(path, bytes).unwrap();
+    db.update(|data| data.counter = 1).unwrap();
+
+    cleanup(path);
+}
+
+#[test]
+fn test_undo_redo_history() {
+    let path = "test_undo_redo.json";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(JsonFileSync::new(path), TestData::default())
+        .unwrap()
+        .with_history(2);
+    assert!(!db.undo().unwrap());
+
+    for counter in 1..=3 {
+        db.update(|data| data.counter = counter).unwrap();
+    }
+    // Bounded to the last two updates
+    assert_eq!(db.history_len(), 2);
+
+    assert!(db.undo().unwrap());
+    assert_eq!(db.data().counter, 2);
+    assert!(db.undo().unwrap());
+    assert_eq!(db.data().counter, 1);
+    assert!(!db.undo().unwrap());
+
+    // Undo is persisted
+    let stored = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(stored.data().counter, 1);
+
+    assert!(db.redo().unwrap());
+    assert_eq!(db.data().counter, 2);
+
+    // A new update discards what could be redone
+    db.update(|data| data.counter = 10).unwrap();
+    assert!(!db.redo().unwrap());
+    assert!(db.undo().unwrap());
+    assert_eq!(db.data().counter, 2);
+
+    cleanup(path);
+}
+
+#[test]
+fn test_history_disabled_by_default() {
+    let mut db = SaberDBSync::new(MemorySync::new(), TestData::default()).unwrap();
+    db.update(|data| data.counter = 1).unwrap();
+    assert_eq!(db.history_len(), 0);
+    assert!(!db.undo().unwrap());
+    assert_eq!(db.data().counter, 1);
+}
+
+#[test]
+fn test_snapshots_create_list_restore() {
+    let path = "test_snapshots.json";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert!(db.list_snapshots().unwrap().is_empty());
+
+    db.update(|data| data.counter = 1).unwrap();
+    db.snapshot("before-migration").unwrap();
+    assert!(std::path::Path::new("test_snapshots.snapshots/before-migration.json").exists());
+    db.update(|data| data.counter = 2).unwrap();
+    db.snapshot("after").unwrap();
+
+    // Snapshots are immutable
+    assert!(matches!(
+        db.snapshot("after"),
+        Err(SaberError::SnapshotExists(name)) if name == "after"
+    ));
+    assert!(db.snapshot("../escape").is_err());
+    assert_eq!(db.list_snapshots().unwrap(), vec!["after", "before-migration"]);
+
+    db.update(|data| data.counter = 99).unwrap();
+    db.restore("before-migration").unwrap();
+    assert_eq!(db.data().counter, 1);
+    let stored = SaberDBSync::new(JsonFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(stored.data().counter, 1);
+
+    assert!(matches!(
+        db.restore("missing"),
+        Err(SaberError::SnapshotNotFound(_))
+    ));
+
+    // Dotted names are listed whether or not the file has an extension
+    let bare = "test_snapshots_bare";
+    cleanup(bare);
+    let db2 = SaberDBSync::new(JsonFileSync::new(bare), TestData::default()).unwrap();
+    db.snapshot("v1.2").unwrap();
+    db2.snapshot("v1.2").unwrap();
+    assert_eq!(db.list_snapshots().unwrap(), vec!["after", "before-migration", "v1.2"]);
+    assert_eq!(db2.list_snapshots().unwrap(), vec!["v1.2"]);
+
+    cleanup(path);
+    cleanup(bare);
+}
+
+#[test]
+fn test_snapshots_in_memory_and_unsupported() {
+    let mut db = SaberDBSync::new(MemorySync::new(), TestData::default()).unwrap();
+    db.snapshot("empty").unwrap();
+    db.update(|data| data.counter = 5).unwrap();
+    db.restore("empty").unwrap();
+    assert_eq!(db.data().counter, 0);
+    assert_eq!(db.list_snapshots().unwrap(), vec!["empty"]);
+
+    let path = "test_snapshots_wal.json";
+    cleanup(path);
+    let db = SaberDBSync::new(WalFileSync::new(path), TestData::default()).unwrap();
+    assert!(matches!(
+        db.snapshot("x"),
+        Err(SaberError::Unsupported("snapshots"))
+    ));
+    cleanup(path);
+}
+
+/// JSON on one line, with a marker so the format is recognisable on disk
+#[derive(Default)]
+struct TaggedJson;
+
+impl Format for TaggedJson {
+    fn encode<T: Serialize>(&self, data: &T) -> saberdb::Result<Vec<u8>> {
+        let mut bytes = b"tagged:".to_vec();
+        bytes.extend(serde_json::to_vec(data)?);
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> saberdb::Result<T> {
+        let json = bytes
+            .strip_prefix(b"tagged:")
+            .ok_or_else(|| SaberError::Serialization("missing tag".into()))?;
+        Ok(serde_json::from_slice(json)?)
+    }
+}
+
+#[test]
+fn test_custom_format_file_adapter() {
+    let path = "test_custom_format.json";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(
+        FileSync::<TaggedJson>::new(path).with_backups(1),
+        TestData::default(),
+    )
+    .unwrap();
+    db.update(|data| data.counter = 1).unwrap();
+    db.update(|data| data.counter = 2).unwrap();
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        r#"tagged:{"counter":2,"message":"default"}"#
+    );
+    db.restore_backup(1).unwrap();
+    assert_eq!(db.data().counter, 1);
+
+    // Recovery works the same for any format
+    fs::write(path, r#"{"counter": 5}"#).unwrap();
+    let result = SaberDBSync::new(FileSync::<TaggedJson>::new(path), TestData::default());
+    assert!(matches!(result, Err(SaberError::Serialization(_))));
+
+    cleanup(path);
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_yaml_file_roundtrip() {
+    use saberdb::YamlFileSync;
+
+    let path = "test_yaml.yaml";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(YamlFileSync::new(path).with_backups(1), TestData::default())
+        .unwrap();
+    db.update(|data| data.counter = 3).unwrap();
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        "counter: 3\nmessage: default\n"
+    );
+
+    // Hand edits are picked up like any other change
+    fs::write(path, "# edited by ops\ncounter: 4\nmessage: by hand\n").unwrap();
+    db.reload().unwrap();
+    assert_eq!(db.data().counter, 4);
+    assert_eq!(db.data().message, "by hand");
+
+    fs::write(path, "counter: [").unwrap();
+    let result = SaberDBSync::new(YamlFileSync::new(path), TestData::default());
+    assert!(matches!(result, Err(SaberError::Serialization(_))));
+
+    cleanup(path);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn test_toml_file_roundtrip_and_unrepresentable_data() {
+    use saberdb::TomlFileSync;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+    struct Config {
+        name: String,
+        nickname: Option<String>,
+        limits: BTreeMap<String, Option<u64>>,
+        ports: Vec<Option<u16>>,
+    }
+
+    let path = "test_toml.toml";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(TomlFileSync::new(path), Config::default()).unwrap();
+    db.update(|config| {
+        config.name = "api".to_string();
+        config.limits.insert("requests".to_string(), Some(100));
+        config.ports = vec![Some(80), Some(443)];
+    })
+    .unwrap();
+    let reopened = SaberDBSync::new(TomlFileSync::new(path), Config::default()).unwrap();
+    assert_eq!(reopened.data(), db.data());
+    assert!(fs::read_to_string(path).unwrap().contains("name = \"api\""));
+
+    let result = db.update(|config| config.ports.push(None));
+    assert!(matches!(
+        result,
+        Err(SaberError::Unrepresentable { format: "TOML", reason }) if reason.contains("/ports/2")
+    ));
+    db.data_mut().ports.pop();
+
+    let result = db.update(|config| {
+        config.limits.insert("bytes".to_string(), Some(u64::MAX));
+    });
+    assert!(matches!(
+        result,
+        Err(SaberError::Unrepresentable { reason, .. }) if reason.contains("/limits/bytes")
+    ));
+
+    let list = SaberDBSync::new(TomlFileSync::new("test_toml_list.toml"), vec![1, 2, 3]).unwrap();
+    assert!(matches!(
+        list.write(),
+        Err(SaberError::Unrepresentable { reason, .. }) if reason.contains("an array")
+    ));
+
+    cleanup(path);
+    cleanup("test_toml_list.toml");
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn test_msgpack_file_roundtrip_is_smaller_than_json() {
+    use saberdb::MessagePackFileSync;
+
+    let path = "test_msgpack.msgpack";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(MessagePackFileSync::new(path), TestData::default()).unwrap();
+    db.update(|data| data.counter = 42).unwrap();
+
+    let reopened = SaberDBSync::new(MessagePackFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(reopened.data(), db.data());
+    let json = serde_json::to_vec_pretty(db.data()).unwrap();
+    assert!(fs::read(path).unwrap().len() < json.len());
+
+    cleanup(path);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_cbor_file_roundtrip_with_backups() {
+    use saberdb::CborFileSync;
+
+    let path = "test_cbor.cbor";
+    cleanup(path);
+
+    let adapter = CborFileSync::new(path).with_backups(1);
+    let mut db = SaberDBSync::new(adapter, TestData::default()).unwrap();
+    db.update(|data| data.counter = 1).unwrap();
+    db.update(|data| data.counter = 2).unwrap();
+
+    let reopened = SaberDBSync::new(CborFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(reopened.data().counter, 2);
+    db.restore_backup(1).unwrap();
+    assert_eq!(db.data().counter, 1);
+
+    cleanup(path);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn test_bincode_file_roundtrip_and_trailing_bytes() {
+    use saberdb::BincodeFileSync;
+
+    let path = "test_bincode.bin";
+    cleanup(path);
+
+    let mut db = SaberDBSync::new(BincodeFileSync::new(path), TestData::default()).unwrap();
+    db.update(|data| data.message = "binary".to_string()).unwrap();
+
+    let reopened = SaberDBSync::new(BincodeFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(reopened.data(), db.data());
+
+    let mut bytes = fs::read(path).unwrap();
+    bytes.push(0);
+    fs::write(path, bytes).unwrap();
+    assert!(matches!(
+        SaberDBSync::new(BincodeFileSync::new(path), TestData::default()),
+        Err(SaberError::Serialization(_))
+    ));
+
+    cleanup(path);
+}
+
+#[test]
+fn test_jsonc_file_keeps_top_level_comments() {
+    use saberdb::JsoncFileSync;
+
+    let path = "test_jsonc.json";
+    cleanup(path);
+    fs::write(
+        path,
+        r#"// App settings
+{
+  // Shown in the title bar
+  "message": "see http://example.com", // keep it short
+  /* Bumped on
+     every save */
+  "counter": 1,
+  // more to come
+}
+"#,
+    )
+    .unwrap();
+
+    let mut db = SaberDBSync::new(JsoncFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(db.data().message, "see http://example.com");
+    db.update(|data| data.counter += 1).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        r#"// App settings
+{
+  /* Bumped on
+     every save */
+  "counter": 2,
+  // Shown in the title bar
+  "message": "see http://example.com" // keep it short
+  // more to come
+}"#
+    );
+    let reopened = SaberDBSync::new(JsoncFileSync::new(path), TestData::default()).unwrap();
+    assert_eq!(reopened.data(), db.data());
+
+    cleanup(path);
+}